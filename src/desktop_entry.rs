@@ -0,0 +1,536 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use gio::prelude::FileExt;
+use walkdir::WalkDir;
+
+/// The parts of a freedesktop `.desktop` file the launcher cares about,
+/// read from the `[Desktop Entry]` group with localized strings resolved
+/// for the current locale.
+#[derive(Debug, Clone, Default)]
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. `org.gnome.Nautilus.desktop` or
+    /// `kde4-okular.desktop` for `applications/kde4/okular.desktop`.
+    pub id: String,
+    pub path: PathBuf,
+    pub entry_type: String,
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub comment: Option<String>,
+    pub keywords: Vec<String>,
+    pub icon: Option<String>,
+    pub categories: Vec<String>,
+    /// MIME types the application can open.
+    pub mime_types: Vec<String>,
+    pub exec: Option<String>,
+    pub try_exec: Option<String>,
+    pub working_dir: Option<String>,
+    pub terminal: bool,
+    pub hidden: bool,
+    pub no_display: bool,
+    pub only_show_in: Vec<String>,
+    pub not_show_in: Vec<String>,
+    pub actions: Vec<DesktopAction>,
+}
+
+/// A `[Desktop Action <id>]` group listed in the entry's `Actions=` key,
+/// e.g. "New Private Window".
+#[derive(Debug, Clone, Default)]
+pub struct DesktopAction {
+    pub id: String,
+    pub name: String,
+    pub icon: Option<String>,
+    pub exec: Option<String>,
+}
+
+type Group = HashMap<String, String>;
+
+impl DesktopEntry {
+    pub fn from_file(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path).ok()?;
+        Self::parse(&content, path)
+    }
+
+    pub fn parse(content: &str, path: &Path) -> Option<Self> {
+        let groups = parse_groups(content);
+        let group = groups.get("Desktop Entry")?;
+        let locales = locale_variants();
+
+        Some(Self {
+            id: path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            path: path.to_path_buf(),
+            entry_type: group.get("Type").cloned().unwrap_or_default(),
+            name: localized(group, "Name", &locales).map(|v| unescape(&v))?,
+            generic_name: localized(group, "GenericName", &locales).map(|v| unescape(&v)),
+            comment: localized(group, "Comment", &locales).map(|v| unescape(&v)),
+            keywords: localized(group, "Keywords", &locales).map(|v| split_list(&v)).unwrap_or_default(),
+            icon: group.get("Icon").map(|v| unescape(v)),
+            categories: group.get("Categories").map(|v| split_list(v)).unwrap_or_default(),
+            mime_types: group.get("MimeType").map(|v| split_list(v)).unwrap_or_default(),
+            exec: group.get("Exec").map(|v| unescape(v)),
+            try_exec: group.get("TryExec").map(|v| unescape(v)),
+            working_dir: group.get("Path").map(|v| unescape(v)),
+            terminal: is_true(group.get("Terminal")),
+            hidden: is_true(group.get("Hidden")),
+            no_display: is_true(group.get("NoDisplay")),
+            only_show_in: group.get("OnlyShowIn").map(|v| split_list(v)).unwrap_or_default(),
+            not_show_in: group.get("NotShowIn").map(|v| split_list(v)).unwrap_or_default(),
+            actions: parse_actions(group, &groups, &locales),
+        })
+    }
+
+    pub fn action(&self, id: &str) -> Option<&DesktopAction> {
+        self.actions.iter().find(|a| a.id == id)
+    }
+
+    /// Whether the entry should be offered as an app: a displayable
+    /// `Application` meant for the current desktop whose `TryExec`
+    /// binary is installed.
+    pub fn should_show(&self) -> bool {
+        if self.entry_type != "Application" || self.hidden || self.no_display {
+            return false;
+        }
+
+        let desktops = current_desktops();
+        if !self.only_show_in.is_empty() && !self.only_show_in.iter().any(|d| desktops.contains(d)) {
+            return false;
+        }
+        if self.not_show_in.iter().any(|d| desktops.contains(d)) {
+            return false;
+        }
+
+        match &self.try_exec {
+            Some(try_exec) => find_executable(try_exec).is_some(),
+            None => true,
+        }
+    }
+
+    /// Strings the query is matched against, most relevant first.
+    pub fn search_terms(&self) -> Vec<&str> {
+        let mut terms = vec![self.name.as_str()];
+        terms.extend(self.generic_name.as_deref());
+        terms.extend(self.keywords.iter().map(String::as_str));
+        terms.extend(self.categories.iter().map(String::as_str));
+        terms
+    }
+}
+
+/// Reads every `.desktop` file under `app_dirs`, which are given in
+/// precedence order. The first file seen for a desktop file ID shadows the
+/// others, so a copy in `~/.local/share/applications` (even a `Hidden` one)
+/// overrides the system entry. Entries that shouldn't be shown are dropped
+/// after shadowing.
+pub fn load_entries(app_dirs: &[String]) -> Vec<DesktopEntry> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+
+    for dir in app_dirs {
+        let root = Path::new(dir);
+        if !root.is_dir() {
+            continue;
+        }
+
+        for file in WalkDir::new(root).follow_links(true).into_iter().filter_map(Result::ok) {
+            let path = file.path();
+            if !file.file_type().is_file() || path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+                continue;
+            }
+
+            let Ok(relative) = path.strip_prefix(root) else {
+                continue;
+            };
+            let id = relative.to_string_lossy().replace('/', "-");
+            if !seen.insert(id.clone()) {
+                continue;
+            }
+
+            if let Some(mut entry) = DesktopEntry::from_file(path) {
+                entry.id = id;
+                if entry.should_show() {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// Turns an `Exec` value into the argv of each process to start, with
+/// field codes expanded against `entry` and `targets` (paths or URIs).
+/// Usually that is one command; `%f`/`%u` with several targets starts one
+/// instance per target, as the spec requires.
+pub fn expand_exec(exec: &str, entry: &DesktopEntry, targets: &[String]) -> Vec<Vec<String>> {
+    let Some(args) = split_exec(exec) else {
+        return Vec::new();
+    };
+
+    let single = args.iter().any(|arg| arg.contains("%f") || arg.contains("%u"));
+    if single && targets.len() > 1 {
+        return targets
+            .iter()
+            .map(|target| expand_args(&args, entry, std::slice::from_ref(target)))
+            .collect();
+    }
+
+    vec![expand_args(&args, entry, targets)]
+}
+
+/// Splits an `Exec` value on unquoted whitespace. Inside double quotes a
+/// backslash escapes the next character. `None` on an unterminated quote.
+fn split_exec(exec: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => current.push(chars.next()?),
+                        other => current.push(other),
+                    }
+                }
+            }
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            _ => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+
+    Some(args)
+}
+
+fn expand_args(args: &[String], entry: &DesktopEntry, targets: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "%F" => out.extend(targets.iter().filter_map(|t| target_path(t))),
+            "%U" => out.extend(targets.iter().map(|t| target_uri(t))),
+            "%i" => {
+                if let Some(icon) = &entry.icon {
+                    out.push(String::from("--icon"));
+                    out.push(icon.clone());
+                }
+            }
+            _ => {
+                let expanded = expand_codes(arg, entry, targets);
+                // An argument made only of codes that expanded to nothing
+                // (e.g. `%f` without files) is dropped rather than passed as ""
+                if !expanded.is_empty() || arg.is_empty() {
+                    out.push(expanded);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn expand_codes(arg: &str, entry: &DesktopEntry, targets: &[String]) -> String {
+    let mut out = String::with_capacity(arg.len());
+    let mut chars = arg.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => out.push('%'),
+            Some('f') | Some('F') => out.extend(targets.first().and_then(|t| target_path(t))),
+            Some('u') | Some('U') => out.extend(targets.first().map(|t| target_uri(t))),
+            Some('c') => out.push_str(&entry.name),
+            Some('k') => out.push_str(&entry.path.to_string_lossy()),
+            // %i outside its own argument, and the deprecated %d %D %n %N %v %m
+            _ => {}
+        }
+    }
+
+    out
+}
+
+fn target_path(target: &str) -> Option<String> {
+    if target.contains("://") {
+        gio::File::for_uri(target)
+            .path()
+            .map(|p| p.to_string_lossy().to_string())
+    } else {
+        Some(target.to_string())
+    }
+}
+
+fn target_uri(target: &str) -> String {
+    if target.contains("://") {
+        target.to_string()
+    } else {
+        gio::File::for_path(target).uri().to_string()
+    }
+}
+
+fn parse_groups(content: &str) -> HashMap<String, Group> {
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name.to_string());
+            groups.entry(name.to_string()).or_default();
+            continue;
+        }
+
+        if let (Some(group), Some((key, value))) = (&current, line.split_once('=')) {
+            // First occurrence wins, like most desktop entry readers
+            groups
+                .entry(group.clone())
+                .or_default()
+                .entry(key.trim().to_string())
+                .or_insert_with(|| value.trim().to_string());
+        }
+    }
+
+    groups
+}
+
+fn parse_actions(group: &Group, groups: &HashMap<String, Group>, locales: &[String]) -> Vec<DesktopAction> {
+    let ids = group.get("Actions").map(|v| split_list(v)).unwrap_or_default();
+
+    ids.into_iter()
+        .filter_map(|id| {
+            let action = groups.get(&format!("Desktop Action {}", id))?;
+            Some(DesktopAction {
+                name: localized(action, "Name", locales).map(|v| unescape(&v))?,
+                icon: action.get("Icon").map(|v| unescape(v)),
+                exec: action.get("Exec").map(|v| unescape(v)),
+                id,
+            })
+        })
+        .collect()
+}
+
+/// Locale suffixes to try for `Key[locale]`, in the order the spec
+/// prescribes: lang_COUNTRY@MODIFIER, lang_COUNTRY, lang@MODIFIER, lang.
+fn locale_variants() -> Vec<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|v| !v.is_empty())
+        .unwrap_or_default();
+
+    // Drop the encoding, e.g. "de_DE.UTF-8@euro" -> "de_DE@euro"
+    let (base, modifier) = match locale.split_once('@') {
+        Some((base, modifier)) => (base, Some(modifier)),
+        None => (locale.as_str(), None),
+    };
+    let base = base.split('.').next().unwrap_or("");
+    let (lang, country) = match base.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (base, None),
+    };
+
+    if lang.is_empty() || lang == "C" || lang == "POSIX" {
+        return Vec::new();
+    }
+
+    let mut variants = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        variants.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        variants.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        variants.push(format!("{}@{}", lang, modifier));
+    }
+    variants.push(lang.to_string());
+    variants
+}
+
+fn localized(group: &Group, key: &str, locales: &[String]) -> Option<String> {
+    locales
+        .iter()
+        .find_map(|locale| group.get(&format!("{}[{}]", key, locale)))
+        .or_else(|| group.get(key))
+        .cloned()
+}
+
+fn is_true(value: Option<&String>) -> bool {
+    value.map(|v| v == "true").unwrap_or(false)
+}
+
+/// Resolves the `\s`, `\n`, `\t`, `\r` and `\\` escapes of string values.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => out.push(' '),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+/// Splits a `;`-separated list value, honouring `\;` escapes.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(';') => current.push(';'),
+                Some(other) => {
+                    current.push('\\');
+                    current.push(other);
+                }
+                None => current.push('\\'),
+            },
+            ';' => items.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    items.push(current);
+
+    items
+        .iter()
+        .map(|item| unescape(item.trim()))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn current_desktops() -> Vec<String> {
+    env::var("XDG_CURRENT_DESKTOP")
+        .map(|v| v.split(':').map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Looks up `program` the way a shell would: as-is when it contains a
+/// slash, otherwise in each `$PATH` directory.
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains('/') {
+        let path = PathBuf::from(program);
+        return path.is_file().then_some(path);
+    }
+
+    env::var_os("PATH").and_then(|paths| {
+        env::split_paths(&paths)
+            .map(|dir| dir.join(program))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(extra: &str) -> DesktopEntry {
+        let content = format!("[Desktop Entry]\nType=Application\nName=Viewer\n{}", extra);
+        DesktopEntry::parse(&content, Path::new("/usr/share/applications/viewer.desktop")).unwrap()
+    }
+
+    fn targets(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn split_exec_handles_quotes_and_escapes() {
+        let args = split_exec(r#"viewer "two words" "say \"hi\"" "back\\slash" plain"#).unwrap();
+        assert_eq!(args, ["viewer", "two words", r#"say "hi""#, r"back\slash", "plain"]);
+    }
+
+    #[test]
+    fn split_exec_rejects_unterminated_quote() {
+        assert_eq!(split_exec(r#"viewer "open"#), None);
+    }
+
+    #[test]
+    fn exec_escapes_apply_after_string_unescaping() {
+        // `\\"` in the file is `\"` after string unescaping, a quote in the argument
+        let entry = entry(r#"Exec=sh -c "printf \\"%%s\\" \\\\" %f"#);
+        let commands = expand_exec(entry.exec.as_deref().unwrap(), &entry, &targets(&["/tmp/a"]));
+        assert_eq!(commands, [["sh", "-c", r#"printf "%s" \"#, "/tmp/a"]]);
+    }
+
+    #[test]
+    fn percent_percent_is_a_literal_percent() {
+        let entry = entry("");
+        assert_eq!(expand_exec("viewer --zoom=100%% %f", &entry, &[]), [["viewer", "--zoom=100%"]]);
+    }
+
+    #[test]
+    fn icon_code_expands_to_flag_only_with_icon() {
+        let with_icon = entry("Icon=viewer-icon");
+        assert_eq!(expand_exec("viewer %i", &with_icon, &[]), [["viewer", "--icon", "viewer-icon"]]);
+
+        let without_icon = entry("");
+        assert_eq!(expand_exec("viewer %i", &without_icon, &[]), [["viewer"]]);
+    }
+
+    #[test]
+    fn name_and_location_codes() {
+        let entry = entry("");
+        assert_eq!(
+            expand_exec("viewer --class=%c %k", &entry, &[]),
+            [["viewer", "--class=Viewer", "/usr/share/applications/viewer.desktop"]]
+        );
+    }
+
+    #[test]
+    fn file_list_code_expands_to_one_argument_per_target() {
+        let entry = entry("");
+        let commands = expand_exec("viewer %F", &entry, &targets(&["/tmp/a b.png", "/tmp/c.png"]));
+        assert_eq!(commands, [["viewer", "/tmp/a b.png", "/tmp/c.png"]]);
+    }
+
+    #[test]
+    fn single_file_code_starts_one_command_per_target() {
+        let entry = entry("");
+        let commands = expand_exec("viewer --open %f", &entry, &targets(&["/tmp/a", "/tmp/b"]));
+        assert_eq!(commands, [["viewer", "--open", "/tmp/a"], ["viewer", "--open", "/tmp/b"]]);
+    }
+
+    #[test]
+    fn file_code_without_targets_is_dropped() {
+        let entry = entry("");
+        assert_eq!(expand_exec("viewer %f", &entry, &[]), [["viewer"]]);
+    }
+
+    #[test]
+    fn url_code_turns_paths_into_uris() {
+        let entry = entry("");
+        assert_eq!(expand_exec("viewer %u", &entry, &targets(&["/tmp/a b"])), [["viewer", "file:///tmp/a%20b"]]);
+    }
+}
@@ -0,0 +1,208 @@
+/// Subsequence matcher shared by every provider. A pattern matches when its
+/// characters appear in order in the text (case-insensitively); among all
+/// such alignments the best-scoring one is picked, favouring matches at
+/// word starts, camelCase humps and after path separators, and runs of
+/// consecutive characters. "gimp" matches "GNU Image Manipulation Program"
+/// and "lbo" matches "libreoffice".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub score: i64,
+    /// Char (not byte) indices of the matched characters in the text.
+    pub positions: Vec<usize>,
+}
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_FIRST_CHAR: i64 = 24;
+const BONUS_PATH_SEPARATOR: i64 = 24;
+const BONUS_WORD_START: i64 = 20;
+const BONUS_CAMEL_CASE: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 18;
+const PENALTY_GAP: i64 = 1;
+const PENALTY_LEADING_GAP_MAX: i64 = 10;
+
+/// Texts longer than this are not worth the quadratic work.
+const MAX_TEXT_LEN: usize = 1024;
+
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+    let pattern: Vec<char> = pattern.chars().map(fold_case).collect();
+    if pattern.is_empty() {
+        return Some(Match { score: 0, positions: Vec::new() });
+    }
+
+    let original: Vec<char> = text.chars().take(MAX_TEXT_LEN).collect();
+    let text: Vec<char> = original.iter().copied().map(fold_case).collect();
+    if !is_subsequence(&pattern, &text) {
+        return None;
+    }
+
+    let bonuses: Vec<i64> = (0..original.len()).map(|j| position_bonus(&original, j)).collect();
+    let (m, n) = (pattern.len(), text.len());
+
+    // score[i][j]: best score with pattern[i] matched at text[j].
+    // from[i][j]: where pattern[i - 1] was matched on that best path.
+    let mut score = vec![vec![i64::MIN; n]; m];
+    let mut from = vec![vec![usize::MAX; n]; m];
+
+    for i in 0..m {
+        // Best score[i - 1][k] - gap for k <= j - 2, and its k
+        let mut gapped = i64::MIN;
+        let mut gapped_from = usize::MAX;
+
+        for j in 0..n {
+            if i > 0 && j >= 2 && score[i - 1][j - 2] != i64::MIN {
+                let candidate = score[i - 1][j - 2];
+                if gapped == i64::MIN || candidate > gapped {
+                    gapped = candidate;
+                    gapped_from = j - 2;
+                }
+            }
+            if i > 0 && gapped != i64::MIN && j >= 2 {
+                gapped -= PENALTY_GAP;
+            }
+
+            if pattern[i] != text[j] {
+                continue;
+            }
+
+            let base = SCORE_MATCH + bonuses[j];
+            if i == 0 {
+                score[i][j] = base - (j as i64 * PENALTY_GAP).min(PENALTY_LEADING_GAP_MAX);
+                continue;
+            }
+
+            let mut best = i64::MIN;
+            if gapped != i64::MIN {
+                best = gapped + base;
+                from[i][j] = gapped_from;
+            }
+            if j > 0 && score[i - 1][j - 1] != i64::MIN {
+                let consecutive = score[i - 1][j - 1] + base + BONUS_CONSECUTIVE;
+                if consecutive >= best {
+                    best = consecutive;
+                    from[i][j] = j - 1;
+                }
+            }
+            score[i][j] = best;
+        }
+    }
+
+    let (mut j, best) = score[m - 1]
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, s)| *s != i64::MIN)
+        .max_by_key(|(j, s)| (*s, std::cmp::Reverse(*j)))?;
+
+    let mut positions = vec![0; m];
+    for i in (0..m).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+
+    Some(Match { score: best, positions })
+}
+
+/// Best match of `pattern` against any of `texts`; earlier texts count
+/// more, so a hit in an app's name beats one in its keywords.
+pub fn best_match<'a>(pattern: &str, texts: impl IntoIterator<Item = &'a str>) -> Option<(usize, Match)> {
+    texts
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, text)| {
+            fuzzy_match(pattern, text).map(|mut m| {
+                m.score -= i as i64 * 8;
+                (i, m)
+            })
+        })
+        .max_by_key(|(i, m)| (m.score, std::cmp::Reverse(*i)))
+}
+
+fn fold_case(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn is_subsequence(pattern: &[char], text: &[char]) -> bool {
+    let mut chars = text.iter();
+    pattern.iter().all(|p| chars.any(|c| c == p))
+}
+
+fn position_bonus(text: &[char], j: usize) -> i64 {
+    if j == 0 {
+        return BONUS_FIRST_CHAR;
+    }
+
+    let (prev, cur) = (text[j - 1], text[j]);
+    match prev {
+        '/' => BONUS_PATH_SEPARATOR,
+        ' ' | '-' | '_' | '.' | ':' => BONUS_WORD_START,
+        _ if prev.is_lowercase() && cur.is_uppercase() => BONUS_CAMEL_CASE,
+        _ if !prev.is_alphanumeric() && cur.is_alphanumeric() => BONUS_WORD_START,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(pattern: &str, text: &str) -> Vec<usize> {
+        fuzzy_match(pattern, text).unwrap().positions
+    }
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text).unwrap().score
+    }
+
+    #[test]
+    fn matches_word_initials() {
+        assert_eq!(positions("gimp", "GNU Image Manipulation Program"), [0, 4, 10, 23]);
+    }
+
+    #[test]
+    fn matches_scattered_subsequence() {
+        assert_eq!(positions("lbo", "libreoffice"), [0, 2, 5]);
+    }
+
+    #[test]
+    fn rejects_out_of_order_characters() {
+        assert_eq!(fuzzy_match("bl", "libreoffice"), None);
+        assert_eq!(fuzzy_match("xyz", "libreoffice"), None);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        assert_eq!(fuzzy_match("", "anything"), Some(Match { score: 0, positions: Vec::new() }));
+    }
+
+    #[test]
+    fn ignores_case() {
+        assert_eq!(positions("FIRE", "firefox"), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn positions_are_char_indices() {
+        assert_eq!(positions("ler", "Müller"), [3, 4, 5]);
+        assert_eq!(positions("mül", "Müller"), [0, 1, 2]);
+    }
+
+    #[test]
+    fn prefers_consecutive_run_over_scattered_characters() {
+        assert_eq!(positions("abc", "axbxc abc"), [6, 7, 8]);
+        assert!(score("abc", "abcxxxx") > score("abc", "axbxcxx"));
+    }
+
+    #[test]
+    fn prefers_word_starts() {
+        assert!(score("fb", "foo bar") > score("fb", "xfxxxbx"));
+        assert!(score("fb", "foo/bar") > score("fb", "foobar"));
+        assert!(score("fb", "fooBar") > score("fb", "foobar"));
+    }
+
+    #[test]
+    fn best_match_favours_earlier_texts() {
+        let (term, _) = best_match("term", ["Terminal", "Terminal Emulator"]).unwrap();
+        assert_eq!(term, 0);
+        let (term, _) = best_match("emu", ["Terminal", "Terminal Emulator"]).unwrap();
+        assert_eq!(term, 1);
+    }
+}
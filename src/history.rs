@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::providers::{Payload, SearchResult};
+
+/// Visits kept per item for the recency part of the score.
+const MAX_VISITS: usize = 10;
+/// Items kept in the file; the least frecent are dropped beyond this.
+const MAX_ENTRIES: usize = 1000;
+/// How strongly frecency moves a result relative to its match score.
+const FRECENCY_WEIGHT: f64 = 15.0;
+
+/// Launch history, stored in `$XDG_STATE_HOME/search/history.json`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    entries: HashMap<String, HistoryEntry>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct HistoryEntry {
+    count: u64,
+    /// Unix timestamps of the most recent launches, newest last.
+    visits: Vec<u64>,
+}
+
+impl History {
+    pub fn load() -> Self {
+        fs::read_to_string(history_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) {
+        let path = history_path();
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        match serde_json::to_string(self) {
+            Ok(json) => {
+                if let Err(e) = fs::write(&path, json) {
+                    eprintln!("Failed to save history to {}: {}", path.display(), e);
+                }
+            }
+            Err(e) => eprintln!("Failed to serialize history: {}", e),
+        }
+    }
+
+    /// Firefox-style frecency: the launch count weighted by how recent
+    /// the sampled visits are.
+    pub fn frecency(&self, payload: &Payload) -> f64 {
+        let Some(entry) = self.entries.get(&history_key(payload)) else {
+            return 0.0;
+        };
+        entry_frecency(entry, now())
+    }
+
+    /// Adds each result's frecency to its score.
+    pub fn boost(&self, results: &mut [SearchResult]) {
+        for result in results {
+            let frecency = self.frecency(&result.payload);
+            if frecency > 0.0 {
+                result.score += (frecency.ln_1p() * FRECENCY_WEIGHT) as i64;
+            }
+        }
+    }
+
+    fn record(&mut self, payload: &Payload) {
+        let entry = self.entries.entry(history_key(payload)).or_default();
+        entry.count += 1;
+        entry.visits.push(now());
+        if entry.visits.len() > MAX_VISITS {
+            entry.visits.remove(0);
+        }
+
+        if self.entries.len() > MAX_ENTRIES {
+            let now = now();
+            let mut ranked: Vec<(String, f64)> = self
+                .entries
+                .iter()
+                .map(|(key, entry)| (key.clone(), entry_frecency(entry, now)))
+                .collect();
+            ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+            for (key, _) in ranked.into_iter().take(self.entries.len() - MAX_ENTRIES) {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+/// Records a launch of `payload` in the history file.
+pub fn record(payload: &Payload) {
+    let mut history = History::load();
+    history.record(payload);
+    history.save();
+}
+
+fn history_path() -> PathBuf {
+    dirs::state_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/state")))
+        .unwrap_or_else(|| PathBuf::from("~/.local/state"))
+        .join("search/history.json")
+}
+
+fn history_key(payload: &Payload) -> String {
+    match payload {
+        Payload::DesktopEntry(path) => format!("app:{}", path.display()),
+        Payload::DesktopAction { entry, action } => format!("app:{}#{}", entry.display(), action),
+        Payload::File(path) | Payload::FileLine { path, .. } | Payload::OpenWith { file: path, .. } => {
+            format!("file:{}", path.display())
+        }
+        Payload::Directory(path) => format!("dir:{}", path.display()),
+        Payload::Terminal(path) => format!("terminal:{}", path.display()),
+        Payload::FileAction { path, .. } => format!("file:{}", path.display()),
+        Payload::WebSearch { engine, query } => format!("web:{}:{}", engine, query),
+        Payload::Url(url) => format!("url:{}", url),
+    }
+}
+
+fn entry_frecency(entry: &HistoryEntry, now: u64) -> f64 {
+    if entry.visits.is_empty() {
+        return 0.0;
+    }
+
+    let weights: f64 = entry
+        .visits
+        .iter()
+        .map(|visit| {
+            let age_days = now.saturating_sub(*visit) / 86_400;
+            match age_days {
+                0..=4 => 100.0,
+                5..=14 => 70.0,
+                15..=31 => 50.0,
+                32..=90 => 30.0,
+                _ => 10.0,
+            }
+        })
+        .sum();
+
+    entry.count as f64 * weights / entry.visits.len() as f64
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -0,0 +1,754 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::UNIX_EPOCH;
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::{Walk, WalkBuilder};
+
+use crate::fuzzy::{fuzzy_match, Match};
+use crate::openers::{FilesConfig, FilesRoot};
+use crate::providers::CancelToken;
+
+/// Entries scanned per cancellation check while searching.
+const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+/// Bumped whenever the on-disk layout changes.
+const CACHE_MAGIC: &[u8] = b"BSIDX\x03";
+
+/// What the walk of one root leaves out: `exclude` globs (the `[files]`
+/// ones and the root's own), files matching none of the root's `include`
+/// globs, hidden entries unless `hidden` is set, and whatever `.gitignore`
+/// and `.ignore` files say unless `gitignore` is turned off.
+struct Filter {
+    hidden: bool,
+    gitignore: bool,
+    follow_symlinks: bool,
+    exclude: Arc<Globs>,
+    include: Option<Arc<Globs>>,
+}
+
+impl Filter {
+    fn new(config: &FilesConfig, root: &FilesRoot) -> Self {
+        let include = (!root.include.is_empty()).then(|| Arc::new(Globs::new(&root.include)));
+
+        Self {
+            hidden: config.hidden,
+            gitignore: config.gitignore,
+            follow_symlinks: root.follow_symlinks,
+            exclude: Arc::new(Globs::new(config.exclude.iter().chain(&root.exclude))),
+            include,
+        }
+    }
+
+    /// Walks `dir` down to `max_depth` levels, `dir` itself included.
+    fn walk(&self, dir: &Path, max_depth: usize) -> Walk {
+        let exclude = self.exclude.clone();
+        let include = self.include.clone();
+
+        WalkBuilder::new(dir)
+            .max_depth(Some(max_depth))
+            .follow_links(self.follow_symlinks)
+            .hidden(!self.hidden)
+            .parents(self.gitignore)
+            .ignore(self.gitignore)
+            .git_ignore(self.gitignore)
+            .git_global(self.gitignore)
+            .git_exclude(self.gitignore)
+            .filter_entry(move |entry| {
+                if entry.depth() == 0 {
+                    return true;
+                }
+                if exclude.is_match(entry.path()) {
+                    return false;
+                }
+                let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+                is_dir || include.as_ref().is_none_or(|include| include.is_match(entry.path()))
+            })
+            .build()
+    }
+
+    /// Whether a directory the walk passed through is itself indexed;
+    /// with `include` globs only matching ones are.
+    fn includes_dir(&self, path: &Path) -> bool {
+        self.include.as_ref().is_none_or(|include| include.is_match(path))
+    }
+}
+
+/// Globs without a `/` match entry names at any depth, ones with a `/`
+/// match whole paths.
+struct Globs {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Globs {
+    fn new<'a>(patterns: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            let pattern = pattern.trim_end_matches('/');
+            let glob = if pattern.contains('/') {
+                GlobBuilder::new(pattern).literal_separator(true).build()
+            } else {
+                Glob::new(pattern)
+            };
+
+            match glob {
+                Ok(glob) if pattern.contains('/') => {
+                    paths.add(glob);
+                }
+                Ok(glob) => {
+                    names.add(glob);
+                }
+                Err(e) => eprintln!("Ignoring invalid glob {:?}: {}", pattern, e),
+            }
+        }
+
+        Self {
+            names: names.build().unwrap_or_else(|_| GlobSet::empty()),
+            paths: paths.build().unwrap_or_else(|_| GlobSet::empty()),
+        }
+    }
+
+    fn is_match(&self, path: &Path) -> bool {
+        path.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(path)
+    }
+}
+
+/// A file or directory that searches can find.
+#[derive(Debug, Clone)]
+pub struct IndexedFile {
+    pub path: Box<str>,
+    pub is_dir: bool,
+    /// Byte offset of the file name within `path`.
+    name_start: usize,
+}
+
+impl IndexedFile {
+    fn new(path: &str, is_dir: bool) -> Self {
+        let name_start = path.rfind('/').map(|i| i + 1).unwrap_or(0);
+        Self { path: path.into(), is_dir, name_start }
+    }
+
+    fn from_path(path: &Path, is_dir: bool) -> Option<Self> {
+        path.to_str().map(|path| Self::new(path, is_dir))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.path[self.name_start..]
+    }
+
+    fn parent(&self) -> &str {
+        parent_dir(&self.path)
+    }
+}
+
+/// A directory whose entries are indexed, with the mtime it had when it
+/// was listed. A changed mtime means entries were added, removed or
+/// renamed in it.
+#[derive(Debug, Clone)]
+struct IndexedDir {
+    path: Box<str>,
+    /// In nanoseconds, so an entry added within the second the directory
+    /// was listed in still changes it.
+    mtime: u64,
+    /// Depth below its root, the root itself being 0.
+    depth: usize,
+}
+
+/// List of every file under the configured roots, built with a parallel
+/// walk and then queried without touching the disk. It is cached in
+/// `$XDG_CACHE_HOME/search/files.idx` and refreshed incrementally by
+/// relisting only the directories whose mtime changed.
+#[derive(Debug, Default, Clone)]
+pub struct FileIndex {
+    files: Vec<IndexedFile>,
+    dirs: Vec<IndexedDir>,
+    /// The config the index was built for; a cache built for other roots,
+    /// depths or ignore rules is discarded.
+    fingerprint: String,
+}
+
+/// The outcome of rescanning some directories of a `FileIndex`, kept
+/// apart from the index until `FileIndex::apply`.
+#[derive(Debug, Default)]
+pub struct IndexChanges {
+    /// Directories gone from disk, dropped along with everything below.
+    removed: Vec<String>,
+    /// Directories listed again, with their new mtime; their files are
+    /// replaced by the ones found.
+    relisted: Vec<(String, u64)>,
+    /// Subdirectories of relisted directories that are no longer there.
+    vanished: Vec<String>,
+    files: Vec<IndexedFile>,
+    dirs: Vec<IndexedDir>,
+}
+
+impl IndexChanges {
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty() && self.relisted.is_empty()
+    }
+}
+
+impl FileIndex {
+    pub fn build(config: &FilesConfig) -> Self {
+        let mut index = Self {
+            fingerprint: fingerprint(config),
+            ..Self::default()
+        };
+
+        for root in &config.roots {
+            let filter = Filter::new(config, root);
+            let (files, dirs) = walk_root(&root.path, root.max_depth, &filter);
+            index.files.extend(files);
+            index.dirs.extend(dirs);
+        }
+
+        index.sort();
+        index
+    }
+
+    /// Loads the cached index if there is one for this config.
+    pub fn load(config: &FilesConfig) -> Option<Self> {
+        let bytes = fs::read(cache_path()).ok()?;
+        let index = decode(&bytes)?;
+        (index.fingerprint == fingerprint(config)).then_some(index)
+    }
+
+    pub fn save(&self) {
+        let path = cache_path();
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+
+        // Write then rename so a crash never leaves a truncated cache
+        let tmp = path.with_extension("idx.tmp");
+        if let Err(e) = fs::write(&tmp, encode(self)).and_then(|_| fs::rename(&tmp, &path)) {
+            eprintln!("Failed to save file index to {}: {}", path.display(), e);
+        }
+    }
+
+    /// Brings the index up to date by relisting directories whose mtime
+    /// changed and dropping ones that are gone. Returns whether anything
+    /// changed.
+    pub fn refresh(&mut self, config: &FilesConfig) -> bool {
+        let changes = self.changes(config);
+        if changes.is_empty() {
+            return false;
+        }
+        self.apply(changes);
+        true
+    }
+
+    /// What `refresh` would change, found without modifying the index, so
+    /// a shared index only needs locking for reading meanwhile.
+    pub fn changes(&self, config: &FilesConfig) -> IndexChanges {
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+
+        for dir in &self.dirs {
+            match dir_mtime(Path::new(&*dir.path)) {
+                None => removed.push(dir.path.to_string()),
+                Some(mtime) if mtime != dir.mtime => changed.push((dir.path.to_string(), dir.depth)),
+                _ => {}
+            }
+        }
+
+        self.scan(changed, removed, config)
+    }
+
+    /// Changes from relisting just `dirs`, e.g. the ones a file watcher
+    /// reported events in. Directories the index doesn't cover are ignored.
+    pub fn changes_in(&self, dirs: &HashSet<String>, config: &FilesConfig) -> IndexChanges {
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+
+        for dir in self.dirs.iter().filter(|d| dirs.contains(&*d.path)) {
+            match dir_mtime(Path::new(&*dir.path)) {
+                None => removed.push(dir.path.to_string()),
+                Some(_) => changed.push((dir.path.to_string(), dir.depth)),
+            }
+        }
+
+        self.scan(changed, removed, config)
+    }
+
+    /// Applies changes found by `changes` or `changes_in`, which must have
+    /// been made against the index as it still is. Returns the directories
+    /// that were newly added to the index.
+    pub fn apply(&mut self, changes: IndexChanges) -> Vec<String> {
+        for dir in &changes.removed {
+            self.remove_tree(dir);
+        }
+
+        let relisted: HashSet<&str> = changes.relisted.iter().map(|(path, _)| path.as_str()).collect();
+        self.files.retain(|f| !relisted.contains(f.parent()));
+        for (path, mtime) in &changes.relisted {
+            if let Some(dir) = self.dirs.iter_mut().find(|d| &*d.path == path) {
+                dir.mtime = *mtime;
+            }
+        }
+        for dir in &changes.vanished {
+            self.remove_tree(dir);
+        }
+
+        let added: Vec<String> = changes.dirs.iter().map(|d| d.path.to_string()).collect();
+        self.files.extend(changes.files);
+        self.dirs.extend(changes.dirs);
+        self.sort();
+        added
+    }
+
+    /// Indexed files, directories left out.
+    pub fn files(&self) -> impl Iterator<Item = &IndexedFile> {
+        self.files.iter().filter(|f| !f.is_dir)
+    }
+
+    /// Paths of the directories whose entries are indexed.
+    pub fn dirs(&self) -> impl Iterator<Item = &str> {
+        self.dirs.iter().map(|d| &*d.path)
+    }
+
+    /// Relists the `changed` directories, walking any new subdirectory
+    /// below them, and notes which of their subdirectories are gone.
+    fn scan(&self, mut changed: Vec<(String, usize)>, removed: Vec<String>, config: &FilesConfig) -> IndexChanges {
+        changed.retain(|(path, _)| !removed.iter().any(|r| is_within(path, r)));
+
+        let known_dirs: HashSet<&str> = self.dirs.iter().map(|d| &*d.path).collect();
+        let mut changes = IndexChanges { removed, ..IndexChanges::default() };
+        let mut present_dirs = HashSet::new();
+
+        let filters: Vec<Filter> = config.roots.iter().map(|root| Filter::new(config, root)).collect();
+
+        for (path, depth) in &changed {
+            let Some(root) = root_of(config, path) else {
+                continue;
+            };
+            let (filter, max_depth) = (&filters[root], config.roots[root].max_depth);
+
+            for entry in filter.walk(Path::new(path), 1).filter_map(Result::ok) {
+                let Some(file_type) = entry.file_type() else {
+                    continue;
+                };
+                if entry.depth() == 0 {
+                    continue;
+                }
+
+                if file_type.is_file() {
+                    changes.files.extend(IndexedFile::from_path(entry.path(), false));
+                } else if file_type.is_dir() {
+                    let Some(child) = entry.path().to_str() else {
+                        continue;
+                    };
+                    if filter.includes_dir(entry.path()) {
+                        changes.files.push(IndexedFile::new(child, true));
+                    }
+                    present_dirs.insert(child.to_string());
+                    if !known_dirs.contains(child) {
+                        walk_tree(Path::new(child), depth + 1, max_depth, filter, &mut changes.files, &mut changes.dirs);
+                    }
+                }
+            }
+
+            changes.relisted.push((path.clone(), dir_mtime(Path::new(path)).unwrap_or(0)));
+        }
+
+        // Subdirectories of a relisted directory that are no longer there
+        let changed_paths: HashSet<&str> = changed.iter().map(|(p, _)| p.as_str()).collect();
+        changes.vanished = self
+            .dirs
+            .iter()
+            .filter(|d| changed_paths.contains(parent_dir(&d.path)) && !present_dirs.contains(&*d.path))
+            .map(|d| d.path.to_string())
+            .collect();
+        changes
+    }
+
+    /// Fuzzy-matches `query` against every file name, split across
+    /// threads. Returns `None` if cancelled part way.
+    pub fn search(&self, query: &str, cancel: &CancelToken) -> Option<Vec<(&IndexedFile, Match)>> {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        let chunk_size = self.files.len().div_ceil(threads).max(CANCEL_CHECK_INTERVAL);
+
+        let chunks: Vec<Option<Vec<(&IndexedFile, Match)>>> = thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .files
+                .chunks(chunk_size)
+                .map(|chunk| scope.spawn(move || search_chunk(chunk, query, cancel)))
+                .collect();
+            handles.into_iter().map(|h| h.join().ok().flatten()).collect()
+        });
+
+        let mut matches = Vec::new();
+        for chunk in chunks {
+            matches.extend(chunk?);
+        }
+        Some(matches)
+    }
+
+    fn sort(&mut self) {
+        self.files.sort_by(|a, b| a.path.cmp(&b.path));
+        self.files.dedup_by(|a, b| a.path == b.path);
+        self.dirs.sort_by(|a, b| a.path.cmp(&b.path));
+        self.dirs.dedup_by(|a, b| a.path == b.path);
+    }
+
+    /// Drops `dir` and everything below it.
+    fn remove_tree(&mut self, dir: &str) {
+        self.files.retain(|f| !is_within(&f.path, dir));
+        self.dirs.retain(|d| !is_within(&d.path, dir));
+    }
+}
+
+fn search_chunk<'a>(files: &'a [IndexedFile], query: &str, cancel: &CancelToken) -> Option<Vec<(&'a IndexedFile, Match)>> {
+    let mut matches = Vec::new();
+
+    for (i, file) in files.iter().enumerate() {
+        if i % CANCEL_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+            return None;
+        }
+        if let Some(matched) = fuzzy_match(query, file.name()) {
+            matches.push((file, matched));
+        }
+    }
+
+    Some(matches)
+}
+
+/// Walks `root` to `max_depth`, handing its top-level directories out to
+/// a pool of threads that each walk one subtree at a time.
+fn walk_root(root: &Path, max_depth: usize, filter: &Filter) -> (Vec<IndexedFile>, Vec<IndexedDir>) {
+    let mut files = Vec::new();
+    let mut dirs = Vec::new();
+    let mut subdirs = Vec::new();
+
+    let Some(mtime) = dir_mtime(root) else {
+        return (files, dirs);
+    };
+    if let Some(path) = root.to_str() {
+        dirs.push(IndexedDir { path: path.into(), mtime, depth: 0 });
+    }
+
+    for entry in filter.walk(root, 1).filter_map(Result::ok) {
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+        if entry.depth() == 0 {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            if filter.includes_dir(entry.path()) {
+                files.extend(IndexedFile::from_path(entry.path(), true));
+            }
+            subdirs.push(entry.into_path());
+        } else if file_type.is_file() {
+            files.extend(IndexedFile::from_path(entry.path(), false));
+        }
+    }
+
+    let queue: Mutex<Vec<PathBuf>> = Mutex::new(subdirs);
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    let walked: Vec<(Vec<IndexedFile>, Vec<IndexedDir>)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut found_files = Vec::new();
+                    let mut found_dirs = Vec::new();
+                    while let Some(dir) = queue.lock().ok().and_then(|mut q| q.pop()) {
+                        walk_tree(&dir, 1, max_depth, filter, &mut found_files, &mut found_dirs);
+                    }
+                    (found_files, found_dirs)
+                })
+            })
+            .collect();
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    for (found_files, found_dirs) in walked {
+        files.extend(found_files);
+        dirs.extend(found_dirs);
+    }
+    (files, dirs)
+}
+
+/// Walks `dir`, which sits `depth` levels below its root, collecting the
+/// files and directories below it down to `max_depth`, and the directories
+/// whose entries were listed.
+fn walk_tree(dir: &Path, depth: usize, max_depth: usize, filter: &Filter, files: &mut Vec<IndexedFile>, dirs: &mut Vec<IndexedDir>) {
+    if depth >= max_depth {
+        return;
+    }
+
+    for entry in filter.walk(dir, max_depth - depth).filter_map(Result::ok) {
+        let entry_depth = depth + entry.depth();
+        let Some(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_file() {
+            files.extend(IndexedFile::from_path(entry.path(), false));
+            continue;
+        }
+        if !file_type.is_dir() {
+            continue;
+        }
+
+        if entry.depth() > 0 && filter.includes_dir(entry.path()) {
+            files.extend(IndexedFile::from_path(entry.path(), true));
+        }
+        if entry_depth < max_depth {
+            let mtime = entry
+                .metadata()
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0);
+            if let Some(path) = entry.path().to_str() {
+                dirs.push(IndexedDir { path: path.into(), mtime, depth: entry_depth });
+            }
+        }
+    }
+}
+
+fn dir_mtime(path: &Path) -> Option<u64> {
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return None;
+    }
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+}
+
+fn is_within(path: &str, dir: &str) -> bool {
+    path == dir || (path.starts_with(dir) && (dir.ends_with('/') || path[dir.len()..].starts_with('/')))
+}
+
+fn parent_dir(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) => "/",
+        Some(i) => &path[..i],
+        None => "",
+    }
+}
+
+/// The root `path` was indexed under; the innermost one when roots nest.
+fn root_of(config: &FilesConfig, path: &str) -> Option<usize> {
+    config
+        .roots
+        .iter()
+        .enumerate()
+        .filter(|(_, root)| root.path.to_str().is_some_and(|root| is_within(path, root)))
+        .max_by_key(|(_, root)| root.path.as_os_str().len())
+        .map(|(i, _)| i)
+}
+
+fn fingerprint(config: &FilesConfig) -> String {
+    format!("{:?}|{:?}|{}|{}", config.roots, config.exclude, config.hidden, config.gitignore)
+}
+
+fn cache_path() -> PathBuf {
+    dirs::cache_dir()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cache")))
+        .unwrap_or_else(|| PathBuf::from("~/.cache"))
+        .join("search/files.idx")
+}
+
+// Cache format: the magic, the fingerprint, then the directories and files
+// as sorted, front-coded path lists: each path stores how many bytes it
+// shares with the previous one and only the differing suffix. Each file is
+// followed by a byte that is 1 for directories. Numbers are LEB128 varints.
+
+fn encode(index: &FileIndex) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(CACHE_MAGIC);
+    write_bytes(&mut out, index.fingerprint.as_bytes());
+
+    write_varint(&mut out, index.dirs.len() as u64);
+    let mut prev: &str = "";
+    for dir in &index.dirs {
+        write_front_coded(&mut out, prev, &dir.path);
+        write_varint(&mut out, dir.mtime);
+        write_varint(&mut out, dir.depth as u64);
+        prev = &dir.path;
+    }
+
+    write_varint(&mut out, index.files.len() as u64);
+    let mut prev: &str = "";
+    for file in &index.files {
+        write_front_coded(&mut out, prev, &file.path);
+        out.push(file.is_dir as u8);
+        prev = &file.path;
+    }
+
+    out
+}
+
+fn decode(bytes: &[u8]) -> Option<FileIndex> {
+    let mut reader = Reader { bytes: bytes.strip_prefix(CACHE_MAGIC)?, pos: 0 };
+    let fingerprint = String::from_utf8(reader.bytes()?.to_vec()).ok()?;
+
+    let dir_count = reader.varint()? as usize;
+    let mut dirs = Vec::with_capacity(dir_count.min(1 << 20));
+    let mut prev = String::new();
+    for _ in 0..dir_count {
+        prev = reader.front_coded(&prev)?;
+        let mtime = reader.varint()?;
+        let depth = reader.varint()? as usize;
+        dirs.push(IndexedDir { path: prev.as_str().into(), mtime, depth });
+    }
+
+    let file_count = reader.varint()? as usize;
+    let mut files = Vec::with_capacity(file_count.min(1 << 22));
+    let mut prev = String::new();
+    for _ in 0..file_count {
+        prev = reader.front_coded(&prev)?;
+        let is_dir = reader.byte()? != 0;
+        files.push(IndexedFile::new(&prev, is_dir));
+    }
+
+    Some(FileIndex { files, dirs, fingerprint })
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn write_front_coded(out: &mut Vec<u8>, prev: &str, path: &str) {
+    let shared = prev
+        .bytes()
+        .zip(path.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    // Keep the split on a char boundary so the suffix stays valid UTF-8
+    let shared = (0..=shared).rev().find(|&i| path.is_char_boundary(i)).unwrap_or(0);
+
+    write_varint(out, shared as u64);
+    write_bytes(out, &path.as_bytes()[shared..]);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.pos)?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let byte = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(byte)
+    }
+
+    fn bytes(&mut self) -> Option<&[u8]> {
+        let len = self.varint()? as usize;
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn front_coded(&mut self, prev: &str) -> Option<String> {
+        let shared = self.varint()? as usize;
+        let mut path = prev.get(..shared)?.to_string();
+        path.push_str(std::str::from_utf8(self.bytes()?).ok()?);
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_index() -> FileIndex {
+        let mut index = FileIndex {
+            files: vec![
+                IndexedFile::new("/home/zoë/café", true),
+                IndexedFile::new("/home/zoë/café/menu.txt", false),
+                IndexedFile::new("/home/zoë/cafè.txt", false),
+                IndexedFile::new("/home/zoë/日本語/メモ.md", false),
+                IndexedFile::new("/home/zoë/日本/x", false),
+            ],
+            dirs: vec![
+                IndexedDir { path: "/home/zoë".into(), mtime: 1_700_000_000, depth: 0 },
+                IndexedDir { path: "/home/zoë/café".into(), mtime: 42, depth: 1 },
+                IndexedDir { path: "/home/zoë/日本語".into(), mtime: u64::MAX, depth: 1 },
+            ],
+            fingerprint: String::from("[roots]|[]|false|true"),
+        };
+        index.sort();
+        index
+    }
+
+    #[test]
+    fn cache_round_trips() {
+        let index = sample_index();
+        let decoded = decode(&encode(&index)).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", index));
+        assert_eq!(decoded.files().map(|f| f.name()).collect::<Vec<_>>(), ["cafè.txt", "menu.txt", "x", "メモ.md"]);
+    }
+
+    #[test]
+    fn front_coding_splits_on_char_boundaries() {
+        // "é" and "è" share their first UTF-8 byte
+        let mut out = Vec::new();
+        write_front_coded(&mut out, "/café", "/cafè");
+        let mut reader = Reader { bytes: &out, pos: 0 };
+        assert_eq!(reader.front_coded("/café").as_deref(), Some("/cafè"));
+        assert_eq!(out[0] as usize, "/caf".len());
+    }
+
+    #[test]
+    fn truncated_cache_is_rejected() {
+        let bytes = encode(&sample_index());
+        for len in 0..bytes.len() {
+            assert!(decode(&bytes[..len]).is_none(), "decoded a cache cut at {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn corrupt_cache_does_not_panic() {
+        let bytes = encode(&sample_index());
+        for i in 0..bytes.len() {
+            for value in [0x00, 0x7f, 0x80, 0xc3, 0xff] {
+                let mut corrupt = bytes.clone();
+                corrupt[i] = value;
+                let _ = decode(&corrupt);
+            }
+        }
+        assert!(decode(b"BSIDX\x01").is_none());
+        assert!(decode(b"not an index").is_none());
+    }
+}
@@ -1,30 +1,42 @@
-use std::fs;
-use std::path::Path;
-use std::process::Command;
+use std::cell::{Cell, RefCell};
 use std::env;
-use std::cell::Cell;
 use std::rc::Rc;
+use std::sync::Arc;
+mod desktop_entry;
+mod fuzzy;
+mod history;
+mod index;
 mod openers;
-use openers::{get_openers};
+mod preview;
+mod providers;
+use providers::{CancelToken, ProviderRegistry, SearchResult};
 use gtk::{
-    gdk_pixbuf::Pixbuf, prelude::*, Application, ApplicationWindow, Box as GtkBox,
-    Entry, Orientation, Label, Image,
+    prelude::*, Application, ApplicationWindow, Box as GtkBox, Entry, Label,
 };
-use gio::{Icon, ThemedIcon, FileIcon, prelude::FileExt};
 use glib::clone;
 use glib::Propagation::{Proceed, Stop};
 
 mod ui;
-use ui::{apply_css, build_main_ui};
+use preview::render_preview;
+use ui::{apply_css, build_main_ui, render_actions, render_results};
+mod watcher;
+mod worker;
+use worker::{SearchResponse, SearchWorker};
 
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if let Some(pos) = args.iter().position(|a| a == "--json") {
+        print_results(&args[pos + 1..].join(" "));
+        return;
+    }
+
     let app = Application::new(Some("com.better.search"), Default::default());
 
     app.connect_activate(|app| {
-        apply_css(); 
-        let (window, entry, mode_label, result_box) = build_main_ui(app);
-        setup_search_ui(&entry, &result_box, &mode_label, &window);
+        apply_css();
+        let (window, entry, mode_label, result_box, preview) = build_main_ui(app);
+        setup_search_ui(&entry, &result_box, &mode_label, preview, &window);
         window.show_all();
         entry.grab_focus();
     });
@@ -32,395 +44,224 @@ fn main() {
     app.run();
 }
 
-pub fn search_files(query: &str) -> Vec<(String, String, Image)> {
-    let mut results = Vec::new();
-
-    let home = env::var("HOME").unwrap_or_else(|_| String::from("~/"));
-    let output = Command::new("find")
-        .arg(&home)
-        .arg("-maxdepth")
-        .arg("10")
-        .arg("-type")
-        .arg("f")
-        .arg("-iname")
-        .arg(format!("*{}*", query))
-        .output();
+/// `search --json <query>` runs the query without opening a window and
+/// prints the results, e.g. for scripting or checking a config change.
+fn print_results(text: &str) {
+    let registry = ProviderRegistry::with_defaults();
+    let (_, _, results) = registry.search(text, &CancelToken::never());
 
-    if let Ok(output) = output {
-        if output.status.success() {
-            if let Ok(stdout) = String::from_utf8(output.stdout) {
-                for line in stdout.lines().take(200) {
-                    let path = line.trim().to_string();
-
-                    if fs::metadata(&path).is_err() {
-                        continue;
-                    }
-
-                    let name = Path::new(&path)
-                        .file_name()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or("Unknown")
-                        .to_string();
-
-                    let icon = get_file_icon(&path);
-                    results.push((name, path, icon));
-                }
-            }
-        }
+    match serde_json::to_string_pretty(&results) {
+        Ok(json) => println!("{}", json),
+        Err(e) => eprintln!("Failed to serialize results: {}", e),
     }
-
-    // Deduplicate exact paths
-    results.sort_by(|a, b| a.1.cmp(&b.1));
-    results.dedup_by(|a, b| a.1 == b.1);
-
-    results
 }
 
-
-pub fn search_apps(query: &str) -> Vec<(String, String, Image)> {
-    let mut results = Vec::new();
-    let openers_config = get_openers();
-    let app_dirs = openers_config.app_dirs.clone();
-
- 
-
-    for dir in app_dirs {    
-        let path = Path::new(&dir);
-        if !path.exists() {
-            continue;
-        }
-
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries.flatten() {
-                if let Some(fname) = entry.file_name().to_str().map(|s| s.to_string()) {
-                    if !fname.ends_with(".desktop") {
-                        continue;
-                    }
-
-                    let name = fname.strip_suffix(".desktop").unwrap_or("");
-                    if name.to_lowercase().starts_with(query) {
-                        let full_path = entry.path().to_string_lossy().to_string();
-                        let icon = Image::from_icon_name(Some("application-x-executable"), gtk::IconSize::SmallToolbar);
-                        results.push((name.to_string(), full_path, icon));
-                    }
-                }
-            }
-        }
-    }
-
-    results
+/// What is currently listed: the entry text and provider that produced
+/// the rows, so activation goes back to the same provider. `expanded` is
+/// set while the actions of that result are shown instead.
+struct ResultState {
+    text: String,
+    provider: &'static str,
+    query: String,
+    results: Vec<SearchResult>,
+    expanded: Option<usize>,
 }
 
-
-fn get_file_icon(filepath: &str) -> Image {
-    let guessed = gio::content_type_guess(Some(Path::new(filepath)), &[]);
-    let content_type = guessed.0;
-    let icon = gio::content_type_get_icon(&content_type);
-    icon_to_image(&icon)
-}
-
-fn icon_to_image(icon: &Icon) -> Image {
-    use gio::prelude::Cast;
-
-    if let Some(themed) = icon.downcast_ref::<ThemedIcon>() {
-        if let Some(name) = themed.names().get(0) {
-            return Image::from_icon_name(Some(name.as_str()), gtk::IconSize::SmallToolbar);
-        }
-    } else if let Some(file_icon) = icon.downcast_ref::<FileIcon>() {
-        if let Some(path) = file_icon.file().path() {
-            if let Ok(pixbuf) = Pixbuf::from_file_at_size(path, 24, 24) {
-                return Image::from_pixbuf(Some(&pixbuf));
-            }
-        }
-    }
-
-    Image::from_icon_name(Some("application-x-executable"), gtk::IconSize::SmallToolbar)
-}
-
-pub fn web_search(query: &str) {
-    let url = format!("https://www.duckduckgo.com/search?q={}", query);
-    Command::new("xdg-open").arg(&url).spawn().ok();
-}
-
-use shell_escape::escape;
-
-pub fn open_with_configured_app(filepath: &str) {
-    let ext = Path::new(filepath)
-        .extension()
-        .and_then(|e| e.to_str())
-        .unwrap_or("")
-        .to_lowercase();
-
-    let escaped = escape(filepath.into()).to_string();
-
-    let openers = get_openers(); 
-    let template = openers.openers.get(ext.as_str());
-
-    let command = match template {
-        Some(cmd) => cmd.replace("{file}", &escaped),
-        None => format!("xdg-open {}", escaped),
-    };
-
-
-    println!("Launching: {}", command);
-
-    let _ = Command::new("sh")
-        .arg("-c")
-        .arg(&command)
-        .spawn()
-        .unwrap_or_else(|e| {
-            eprintln!("Failed to launch: {}", e);
-            std::process::exit(1);
-        });
-}
-
-
-fn launch_desktop_entry(path: &str) {
-    if let Ok(content) = std::fs::read_to_string(path) {
-        if let Some(exec_line) = content.lines().find(|line| line.starts_with("Exec=")) {
-            let command_line = exec_line.trim_start_matches("Exec=")
-                .split_whitespace()
-                .map(|s| s.replace("%U", "").replace("%u", "").replace("%F", "").replace("%f", ""))
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<_>>();
-
-            if let Some((program, args)) = command_line.split_first() {
-                let _ = std::process::Command::new(program)
-                    .args(args)
-                    .spawn();
-            }
-        }
-    }
-}
-
-pub fn setup_search_ui(entry: &Entry, result_box: &GtkBox, mode_label: &Label, window: &ApplicationWindow) {
-    let mode_app = Rc::new(Cell::new(true));
+pub fn setup_search_ui(entry: &Entry, result_box: &GtkBox, mode_label: &Label, preview: Option<GtkBox>, window: &ApplicationWindow) {
+    let registry = Arc::new(ProviderRegistry::with_defaults());
+    let state = Rc::new(RefCell::new(ResultState {
+        text: String::new(),
+        provider: registry.active().id(),
+        query: String::new(),
+        results: Vec::new(),
+        expanded: None,
+    }));
     let selected_index = Rc::new(Cell::new(0));
-
-    refresh_results("".to_string(), result_box, &mode_app, &selected_index);
-    highlight_selected_row(result_box, selected_index.get());
-
-
-    // Toggle mode on Tab key
-    let mode_clone = mode_app.clone();
-    let label_clone = mode_label.clone();
-    let result_box_clone = result_box.clone();
-    let selected_index_clone = selected_index.clone();
-
-    entry.connect_key_press_event(move |_, event| {
-        if event.keyval() == gdk::keys::constants::Tab {
-            let new_mode = !mode_clone.get();
-            mode_clone.set(new_mode);
-    
-            label_clone.set_text(if new_mode {
-                "Apps"
-            } else {
-                "Files"
-            });
-    
-            refresh_results("".to_string(), &result_box_clone, &mode_clone, &selected_index_clone);
-            highlight_selected_row(&result_box_clone, selected_index_clone.get()); // ← 👈 move it here
-            Stop
-        } else {
-            Proceed
+    // Entry text Enter was hit for before its results arrived
+    let pending_activation: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+
+    let worker = Rc::new(SearchWorker::spawn(registry.clone(), clone!(@weak result_box, @weak window, @strong preview, @strong registry, @strong state, @strong selected_index, @strong pending_activation => move |response| {
+        let activate = pending_activation.borrow().as_deref() == Some(response.text.as_str());
+        show_results(response, &result_box, preview.as_ref(), &state, &selected_index);
+        if activate {
+            pending_activation.borrow_mut().take();
+            activate_selected(&registry, &state.borrow(), selected_index.get(), &window);
+            window.close();
         }
-    });
+    })));
 
+    mode_label.set_text(registry.active().name());
+    worker.request("");
 
-
-    entry.connect_key_press_event(clone!(@weak result_box, @strong selected_index => @default-return Proceed, move |_, event| {
-        let children: Vec<_> = result_box.children();
-        if children.is_empty() {
-            return Proceed;
-        }
-
+    entry.connect_key_press_event(clone!(@weak result_box, @weak mode_label, @strong preview, @strong registry, @strong worker, @strong state, @strong selected_index => @default-return Proceed, move |entry, event| {
         match event.keyval() {
+            // Cycle through providers on Tab
+            gdk::keys::constants::Tab => {
+                mode_label.set_text(registry.cycle().name());
+                worker.request(&entry.text());
+            },
             gdk::keys::constants::Up => {
-                let mut idx = selected_index.get();
-                if idx > 0 { idx -= 1; }
-                selected_index.set(idx);
+                let idx = selected_index.get();
+                selected_index.set(idx.saturating_sub(1));
+                highlight_selected_row(&result_box, selected_index.get());
+                update_preview(preview.as_ref(), &state.borrow(), selected_index.get());
             },
             gdk::keys::constants::Down => {
-                let mut idx = selected_index.get();
-                if idx + 1 < children.len() { idx += 1; }
-                selected_index.set(idx);
+                let idx = selected_index.get();
+                if idx + 1 < result_box.children().len() {
+                    selected_index.set(idx + 1);
+                }
+                highlight_selected_row(&result_box, selected_index.get());
+                update_preview(preview.as_ref(), &state.borrow(), selected_index.get());
+            },
+            // Right at the end of the text, or Shift+Enter, opens the
+            // selected result's actions
+            gdk::keys::constants::Right if entry.position() == entry.text_length() as i32 => {
+                if !show_actions(&registry, &result_box, &state, &selected_index) {
+                    return Proceed;
+                }
+            },
+            gdk::keys::constants::Return | gdk::keys::constants::KP_Enter
+                if event.state().contains(gdk::ModifierType::SHIFT_MASK) && state.borrow().expanded.is_none() =>
+            {
+                if !show_actions(&registry, &result_box, &state, &selected_index) {
+                    return Proceed;
+                }
+            },
+            gdk::keys::constants::Left | gdk::keys::constants::Escape if state.borrow().expanded.is_some() => {
+                hide_actions(&result_box, preview.as_ref(), &state, &selected_index);
             },
             _ => return Proceed,
         }
 
-        for (_i, _child) in children.iter().enumerate() {
-           highlight_selected_row(&result_box, selected_index.get()); 
-        }
-
         Stop
     }));
 
-    let window_clone = window.clone();
-    let entry_clone = entry.clone();
-    entry.connect_activate(clone!(@strong entry_clone, @strong window_clone, @strong mode_app, @strong selected_index => move |_| {
-        let query = entry_clone.text();
-        if !query.is_empty() {
-            let results = if mode_app.get() {
-                search_apps(&query)
-            } else {
-                search_files(&query)
-            };
-
-            match results.get(selected_index.get()) {
-                Some((_, path, _)) => {
-                    println!("Selected file: {}", path);
-                    if mode_app.get() {
-                        launch_desktop_entry(path);
-                    } else {
-                        open_with_configured_app(path);
-                    }
-                },
-                None => {
-                    println!("No result selected, doing web search...");
-                    web_search(&query);
-                }
-            }
+    entry.connect_activate(clone!(@weak window, @strong registry, @strong worker, @strong state, @strong selected_index, @strong pending_activation => move |entry| {
+        let text = entry.text().to_string();
+        if text.is_empty() {
+            return;
+        }
 
-            window_clone.close();
+        if state.borrow().text != text {
+            // Enter was hit before the results for the latest text arrived;
+            // searching here would block the main loop, so activate once
+            // the worker delivers them
+            worker.request(&text);
+            *pending_activation.borrow_mut() = Some(text);
+            return;
         }
+
+        activate_selected(&registry, &state.borrow(), selected_index.get(), &window);
+        window.close();
     }));
 
-entry.connect_changed(clone!(@weak result_box, @strong mode_app, @strong selected_index => move |entry| {
-    let query = entry.text().to_string().to_lowercase();
-    result_box.foreach(|child| result_box.remove(child));
-    highlight_selected_row(&result_box, selected_index.get());
-    selected_index.set(0);
+    entry.connect_changed(clone!(@weak mode_label, @strong registry, @strong worker, @strong pending_activation => move |entry| {
+        pending_activation.borrow_mut().take();
+        let text = entry.text();
+        // Say where a `!bang` sends the search before Enter does
+        match registry.bang_target(&text) {
+            Some(engine) => mode_label.set_text(&format!("Web → {}", engine)),
+            None => mode_label.set_text(registry.active().name()),
+        }
+        worker.request(&text);
+    }));
+}
 
-    if query.is_empty() {
+/// Runs the selected result, or the selected action when they are shown.
+/// With nothing to select, the query goes to the fallback provider.
+fn activate_selected(registry: &ProviderRegistry, state: &ResultState, selected_index: usize, window: &ApplicationWindow) {
+    if let Some(expanded) = state.expanded {
+        let result = &state.results[expanded];
+        if let (Some(action), Some(provider)) = (result.actions.get(selected_index), registry.get(state.provider)) {
+            println!("Selected [{}]: {} / {}", provider.id(), result.title, action.title);
+            provider.activate_action(result, action, window.upcast_ref());
+        }
         return;
     }
 
-    let results = if mode_app.get() {
-    search_apps(&query)
-} else {
-    search_files(&query)
-};
-
-if results.is_empty() {
-    let row = GtkBox::new(Orientation::Horizontal, 6);
-    row.style_context().add_class("result-row");
-
-    let icon = Image::from_icon_name(Some("system-search-symbolic"), gtk::IconSize::SmallToolbar);
-    row.pack_start(&icon, false, false, 0);
-
-    let label = Label::new(None);
-    label.style_context().add_class("result-label");
-    label.set_use_markup(true);
-    label.set_xalign(0.0);
-    label.set_line_wrap(true);
-    label.set_max_width_chars(80);
-    label.set_ellipsize(pango::EllipsizeMode::End);
-
-    label.set_markup(&format!(
-        "<span foreground='#aaa'>Search for: \n</span><span foreground='#fff' weight='bold'>{}</span>",
-        glib::markup_escape_text(&query)
-    ));
-
-    row.pack_start(&label, true, true, 0);
-    result_box.pack_start(&row, false, false, 0);
-    row.show_all();
-} else {
-    for (name, path, icon) in results.into_iter().take(50) {
-        let row = GtkBox::new(Orientation::Horizontal, 6);
-        row.style_context().add_class("result-row");
-
-        let label = Label::new(None);
-        label.style_context().add_class("result-label");
-        label.set_use_markup(true);
-        label.set_xalign(0.0);
-        label.set_line_wrap(true);
-        label.set_max_width_chars(80);
-        label.set_ellipsize(pango::EllipsizeMode::End);
-
-        label.set_markup(&format!(
-            "<span foreground='#fff' weight='bold'>{}</span>\n<span size='small' foreground='#888'>{}</span>",
-            glib::markup_escape_text(&name),
-            glib::markup_escape_text(&path)
-        ));
-
-        row.pack_start(&icon, false, false, 0);
-        row.pack_start(&label, true, true, 0);
-        result_box.pack_start(&row, false, false, 0);
-        row.show_all();
+    match (state.results.get(selected_index), registry.get(state.provider)) {
+        (Some(result), Some(provider)) => {
+            println!("Selected [{}]: {}", provider.id(), result.title);
+            provider.activate(result, &state.query);
+        },
+        _ => {
+            if let Some(fallback) = registry.fallback() {
+                println!("No result selected, falling back to {}...", fallback.id());
+                if let Some(result) = fallback.query(&state.query, &CancelToken::never()).first() {
+                    fallback.activate(result, &state.query);
+                }
+            }
+        }
     }
 }
 
-    highlight_selected_row(&result_box, selected_index.get());
-
-}));
-
-
-
-
-
-fn refresh_results(query: String, result_box: &GtkBox, mode_app: &Cell<bool>, selected_index: &Cell<usize>) {
-    result_box.foreach(|child| result_box.remove(child));
+fn show_results(response: SearchResponse, result_box: &GtkBox, preview: Option<&GtkBox>, state: &RefCell<ResultState>, selected_index: &Cell<usize>) {
+    render_results(result_box, &response.results);
     selected_index.set(0);
+    highlight_selected_row(result_box, selected_index.get());
 
-    let results = if mode_app.get() {
-        search_apps(&query)
-    } else {
-        search_files(&query)
+    *state.borrow_mut() = ResultState {
+        text: response.text,
+        provider: response.provider,
+        query: response.query,
+        results: response.results,
+        expanded: None,
     };
+    update_preview(preview, &state.borrow(), 0);
+}
+/// Replaces the list with the actions of the selected result. Returns
+/// false when it has none.
+fn show_actions(registry: &ProviderRegistry, result_box: &GtkBox, state: &RefCell<ResultState>, selected_index: &Cell<usize>) -> bool {
+    let mut state = state.borrow_mut();
+    if state.expanded.is_some() {
+        return false;
+    }
 
-    for (name, path, icon) in results.into_iter().take(50) {
-        let row = GtkBox::new(Orientation::Horizontal, 6);
-        row.style_context().add_class("result-row");
-
-        row.pack_start(&icon, false, false, 0);
-
-        let label = Label::new(None);
-        label.style_context().add_class("result-label");
-        label.set_use_markup(true);
-        label.set_xalign(0.0);
-        label.set_line_wrap(true);
-        label.set_max_width_chars(80);
-        label.set_ellipsize(pango::EllipsizeMode::End);
+    let idx = selected_index.get();
+    let provider = registry.get(state.provider);
+    if let (Some(result), Some(provider)) = (state.results.get_mut(idx), provider) {
+        provider.expand_actions(result);
+    }
+    match state.results.get(idx) {
+        Some(result) if !result.actions.is_empty() => render_actions(result_box, result),
+        _ => return false,
+    }
 
-        label.set_markup(&format!(
-            "<span foreground='#fff' weight='bold'>{}</span>\n<span size='small' foreground='#888'>{}</span>",
-            glib::markup_escape_text(&name),
-            glib::markup_escape_text(&path)
-        ));
+    state.expanded = Some(idx);
+    selected_index.set(0);
+    highlight_selected_row(result_box, 0);
+    true
+}
 
-        row.pack_start(&label, true, true, 0);
-        result_box.pack_start(&row, false, false, 0);
-        row.show_all();
+fn hide_actions(result_box: &GtkBox, preview: Option<&GtkBox>, state: &RefCell<ResultState>, selected_index: &Cell<usize>) {
+    let mut state = state.borrow_mut();
+    if let Some(idx) = state.expanded.take() {
+        render_results(result_box, &state.results);
+        selected_index.set(idx);
+        highlight_selected_row(result_box, idx);
+        update_preview(preview, &state, idx);
     }
 }
 
-
-
+/// Previews the selected result; the actions view keeps the preview of
+/// the result they belong to.
+fn update_preview(preview: Option<&GtkBox>, state: &ResultState, selected_index: usize) {
+    if let Some(preview) = preview {
+        if state.expanded.is_none() {
+            render_preview(preview, state.results.get(selected_index));
+        }
+    }
 }
 
+/// Selection is purely a style class so the row markup (match highlights,
+/// subtitle) is left alone.
 fn highlight_selected_row(result_box: &GtkBox, selected_index: usize) {
-    let children: Vec<_> = result_box.children();
-
-    for (i, child) in children.iter().enumerate() {
-        if let Some(box_) = child.downcast_ref::<GtkBox>() {
-            if let Some(label_widget) = box_.children().get(1) {
-                if let Some(label) = label_widget.downcast_ref::<Label>() {
-                    let markup = format!(
-                        "{}{}{}",
-                        if i == selected_index { "<b>" } else { "" },
-                        glib::markup_escape_text(&label.text()),
-                        if i == selected_index { "</b>" } else { "" }
-                    );
-                    label.set_markup(&markup);
-                }
-            }
-
-            if i == selected_index {
-                box_.style_context().add_class("selected");
-            } else {
-                box_.style_context().remove_class("selected");
-            }
+    for (i, child) in result_box.children().iter().enumerate() {
+        if i == selected_index {
+            child.style_context().add_class("selected");
+        } else {
+            child.style_context().remove_class("selected");
         }
     }
 }
-
@@ -1,17 +1,225 @@
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use globset::Glob;
 use toml::Value;
-use std::path::PathBuf;
-use shellexpand;
+use std::path::{Path, PathBuf};
 
 pub struct OpenersConfig {
+    /// `[openers]`: commands with `{file}` (and `{line}`) placeholders,
+    /// keyed by extension (`pdf`), MIME type (`application/pdf`) or a glob
+    /// over either (`image/*`, `*.tar.gz`).
     pub openers: HashMap<String, String>,
     pub app_dirs: Vec<String>,
+    /// Command opening a file at a line, with `{file}` and `{line}`
+    /// standing for them, for content search results.
+    pub editor: Option<String>,
+    /// Command opening a directory, with `{dir}` standing for it.
+    /// The default application for directories when unset.
+    pub file_manager: Option<String>,
+    /// Command opening a terminal, with `{dir}` standing for the directory
+    /// to start in. `$TERMINAL` run in that directory when unset.
+    pub terminal: Option<String>,
+    /// Show the preview pane next to the results.
+    pub preview: bool,
+    pub files: FilesConfig,
+    pub web: WebConfig,
+}
+
+/// The `[files]` section: where the file index looks and how it is kept
+/// up to date.
+pub struct FilesConfig {
+    pub roots: Vec<FilesRoot>,
+    /// Watch indexed directories with inotify for live updates.
+    pub watch: bool,
+    /// Seconds between rescans when not watching.
+    pub rescan_interval: u64,
+    /// Globs left out of the index under every root. One without a `/`
+    /// matches names at any depth, one with a `/` matches whole paths.
+    pub exclude: Vec<String>,
+    /// Index hidden files and directories too.
+    pub hidden: bool,
+    /// Honour `.gitignore` and `.ignore` files.
+    pub gitignore: bool,
+}
+
+/// A directory the file index covers. In `[files] roots` it is either a
+/// plain path or a table:
+///
+/// ```toml
+/// roots = ["~", { path = "/mnt/data", max_depth = 4, follow_symlinks = true, include = ["*.pdf"] }]
+/// ```
+#[derive(Debug)]
+pub struct FilesRoot {
+    pub path: PathBuf,
+    pub max_depth: usize,
+    pub follow_symlinks: bool,
+    /// When not empty, only files matching one of these are indexed.
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl FilesRoot {
+    fn new(path: &str, max_depth: usize) -> Self {
+        Self {
+            path: expand_path(path),
+            max_depth,
+            follow_symlinks: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    fn parse(value: &Value, max_depth: usize) -> Option<Self> {
+        if let Some(path) = value.as_str() {
+            return Some(Self::new(path, max_depth));
+        }
+
+        let mut root = Self::new(value.get("path")?.as_str()?, max_depth);
+        if let Some(depth) = value.get("max_depth").and_then(|v| v.as_integer()) {
+            root.max_depth = depth.max(1) as usize;
+        }
+        if let Some(follow) = value.get("follow_symlinks").and_then(|v| v.as_bool()) {
+            root.follow_symlinks = follow;
+        }
+        root.include = string_list(value.get("include"));
+        root.exclude = string_list(value.get("exclude"));
+        Some(root)
+    }
+}
+
+const DEFAULT_MAX_DEPTH: usize = 10;
+
+impl Default for FilesConfig {
+    fn default() -> Self {
+        Self {
+            roots: dirs::home_dir()
+                .map(|home| FilesRoot::new(&home.to_string_lossy(), DEFAULT_MAX_DEPTH))
+                .into_iter()
+                .collect(),
+            watch: true,
+            rescan_interval: 300,
+            exclude: vec![String::from(".git"), String::from("node_modules"), String::from("__pycache__")],
+            hidden: false,
+            gitignore: true,
+        }
+    }
+}
+
+/// The `[web]` section: search engines, each reachable by its bang.
+///
+/// ```toml
+/// [web]
+/// default = "google"
+///
+/// [web.engines.docs]
+/// name = "docs.rs"
+/// url = "https://docs.rs/releases/search?query={query}"
+/// bang = "docs"
+/// ```
+pub struct WebConfig {
+    /// Built-in engines first, with any configured under the same id
+    /// replacing them.
+    pub engines: Vec<WebEngine>,
+    /// Id of the engine used without a bang.
+    pub default: String,
+}
+
+pub struct WebEngine {
+    pub id: String,
+    pub name: String,
+    /// URL with `{query}` standing for the search terms.
+    pub url: String,
+    /// Keyword picking this engine when typed as `!keyword`.
+    pub bang: Option<String>,
+}
+
+impl WebEngine {
+    fn new(id: &str, name: &str, url: &str, bang: &str) -> Self {
+        Self {
+            id: String::from(id),
+            name: String::from(name),
+            url: String::from(url),
+            bang: Some(String::from(bang)),
+        }
+    }
+
+    fn parse(id: &str, value: &Value) -> Option<Self> {
+        Some(Self {
+            id: String::from(id),
+            name: value.get("name").and_then(|v| v.as_str()).unwrap_or(id).to_string(),
+            url: value.get("url")?.as_str()?.to_string(),
+            bang: value.get("bang").and_then(|v| v.as_str()).map(|bang| bang.trim_start_matches('!').to_string()),
+        })
+    }
+}
+
+impl Default for WebConfig {
+    fn default() -> Self {
+        Self {
+            engines: vec![
+                WebEngine::new("duckduckgo", "DuckDuckGo", "https://duckduckgo.com/?q={query}", "ddg"),
+                WebEngine::new("google", "Google", "https://www.google.com/search?q={query}", "g"),
+                WebEngine::new("github", "GitHub", "https://github.com/search?q={query}", "gh"),
+                WebEngine::new("wikipedia", "Wikipedia", "https://en.wikipedia.org/w/index.php?search={query}", "w"),
+                WebEngine::new("archwiki", "ArchWiki", "https://wiki.archlinux.org/index.php?search={query}", "aw"),
+                WebEngine::new("crates", "crates.io", "https://crates.io/search?q={query}", "crates"),
+            ],
+            default: String::from("duckduckgo"),
+        }
+    }
+}
+
+impl WebConfig {
+    pub fn engine(&self, id: &str) -> Option<&WebEngine> {
+        self.engines.iter().find(|engine| engine.id == id)
+    }
+
+    /// The configured default, or the first engine when it names none.
+    pub fn default_engine(&self) -> Option<&WebEngine> {
+        self.engine(&self.default).or(self.engines.first())
+    }
+
+    pub fn by_bang(&self, bang: &str) -> Option<&WebEngine> {
+        self.engines.iter().find(|engine| engine.bang.as_deref() == Some(bang))
+    }
+}
+
+impl OpenersConfig {
+    /// The `[openers]` command for a file: by extension first, then exact
+    /// MIME type, then the longest glob matching the MIME type or name.
+    pub fn opener_for(&self, path: &Path, mime: &str) -> Option<&String> {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        if let Some(command) = self.openers.get(&ext).filter(|_| !ext.is_empty()) {
+            return Some(command);
+        }
+        if let Some(command) = self.openers.get(mime) {
+            return Some(command);
+        }
+
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        self.openers
+            .iter()
+            .filter(|(key, _)| key.contains('*'))
+            .filter(|(key, _)| {
+                Glob::new(key)
+                    .map(|glob| glob.compile_matcher())
+                    .is_ok_and(|glob| glob.is_match(mime) || glob.is_match(name))
+            })
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, command)| command)
+    }
 }
 
 pub fn get_openers() -> OpenersConfig {
     let mut openers_map = HashMap::new();
     let mut app_dirs_vec = Vec::new();
+    let mut editor = None;
+    let mut file_manager = None;
+    let mut terminal = None;
+    let mut preview = true;
+    let mut files = FilesConfig::default();
+    let mut web = WebConfig::default();
 
     let path = dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("~/.config"))
@@ -38,12 +246,127 @@ pub fn get_openers() -> OpenersConfig {
             }
         }
 
+            if let Some(section) = parsed.get("config") {
+                editor = section.get("editor").and_then(|v| v.as_str()).map(String::from);
+                file_manager = section.get("file_manager").and_then(|v| v.as_str()).map(String::from);
+                terminal = section.get("terminal").and_then(|v| v.as_str()).map(String::from);
+                if let Some(enabled) = section.get("preview").and_then(|v| v.as_bool()) {
+                    preview = enabled;
+                }
+            }
+
+            if let Some(section) = parsed.get("files") {
+                let max_depth = section
+                    .get("max_depth")
+                    .and_then(|v| v.as_integer())
+                    .map(|depth| depth.max(1) as usize)
+                    .unwrap_or(DEFAULT_MAX_DEPTH);
+                if let Some(array) = section.get("roots").and_then(|v| v.as_array()) {
+                    files.roots = array.iter().filter_map(|item| FilesRoot::parse(item, max_depth)).collect();
+                } else {
+                    for root in &mut files.roots {
+                        root.max_depth = max_depth;
+                    }
+                }
+                if let Some(watch) = section.get("watch").and_then(|v| v.as_bool()) {
+                    files.watch = watch;
+                }
+                if let Some(interval) = section.get("rescan_interval").and_then(|v| v.as_integer()) {
+                    files.rescan_interval = interval.max(10) as u64;
+                }
+                if section.get("exclude").is_some() {
+                    files.exclude = string_list(section.get("exclude"));
+                }
+                if let Some(hidden) = section.get("hidden").and_then(|v| v.as_bool()) {
+                    files.hidden = hidden;
+                }
+                if let Some(gitignore) = section.get("gitignore").and_then(|v| v.as_bool()) {
+                    files.gitignore = gitignore;
+                }
+            }
+
+            if let Some(section) = parsed.get("web") {
+                if let Some(default) = section.get("default").and_then(|v| v.as_str()) {
+                    web.default = default.to_string();
+                }
+                if let Some(table) = section.get("engines").and_then(|v| v.as_table()) {
+                    for engine in table.iter().filter_map(|(id, value)| WebEngine::parse(id, value)) {
+                        match web.engines.iter_mut().find(|e| e.id == engine.id) {
+                            Some(existing) => *existing = engine,
+                            None => web.engines.push(engine),
+                        }
+                    }
+                }
+            }
         }
     }
 
+    if app_dirs_vec.is_empty() {
+        app_dirs_vec = default_app_dirs();
+    }
+
     OpenersConfig {
         openers: openers_map,
         app_dirs: app_dirs_vec,
+        editor,
+        file_manager,
+        terminal,
+        preview,
+        files,
+        web,
     }
 }
 
+/// Tilde-expanded, without a trailing slash, so `"~/"` and `"~"` name the
+/// same root.
+fn expand_path(path: &str) -> PathBuf {
+    Path::new(&*shellexpand::tilde(path)).components().collect()
+}
+
+/// A TOML array of strings, tilde-expanded.
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|item| item.as_str())
+                .map(|item| shellexpand::tilde(item).to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `applications` directories in XDG precedence order: `$XDG_DATA_HOME`
+/// first, then each of `$XDG_DATA_DIRS`, plus the Flatpak and Snap export
+/// directories when the session doesn't already list them.
+pub fn default_app_dirs() -> Vec<String> {
+    let mut data_dirs = Vec::new();
+
+    if let Some(data_home) = dirs::data_dir() {
+        data_dirs.push(data_home);
+    }
+
+    let system_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"));
+    data_dirs.extend(system_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from));
+
+    if let Some(data_home) = dirs::data_dir() {
+        data_dirs.push(data_home.join("flatpak/exports/share"));
+    }
+    data_dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    data_dirs.push(PathBuf::from("/var/lib/snapd/desktop"));
+
+    let mut app_dirs: Vec<String> = Vec::new();
+    for dir in data_dirs {
+        let app_dir = dir.join("applications").to_string_lossy().to_string();
+        if !app_dirs.contains(&app_dir) {
+            app_dirs.push(app_dir);
+        }
+    }
+
+    app_dirs
+}
+
@@ -0,0 +1,307 @@
+use std::cell::RefCell;
+use std::fs::{self, File, Metadata};
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use gtk::{gdk_pixbuf::Pixbuf, pango::EllipsizeMode, prelude::*, Box as GtkBox, Grid, Image, Label};
+
+use crate::providers::{Payload, SearchResult};
+
+pub const PREVIEW_WIDTH: i32 = 280;
+const TEXT_LINES: usize = 20;
+/// Longer lines are cut so the text doesn't widen the pane.
+const TEXT_LINE_CHARS: usize = 40;
+const TEXT_HEAD_BYTES: u64 = 16 * 1024;
+/// How far into a file the lines around a content match are looked for.
+const CONTEXT_SCAN_BYTES: u64 = 1024 * 1024;
+/// Lines shown before the matching line of a content search result.
+const CONTEXT_BEFORE: usize = 6;
+const DIR_ENTRIES: usize = 16;
+/// Images larger than this are only shown from the thumbnail cache.
+const MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+
+thread_local! {
+    /// The image or listing still loading for the previous selection,
+    /// cancelled when the selection moves on.
+    static LOADING: RefCell<Option<glib::JoinHandle<()>>> = const { RefCell::new(None) };
+}
+
+/// Fills `preview` with the selected result, or hides it when there's
+/// nothing to preview.
+pub fn render_preview(preview: &GtkBox, result: Option<&SearchResult>) {
+    if let Some(loading) = LOADING.with(|loading| loading.take()) {
+        loading.abort();
+    }
+    for child in preview.children() {
+        preview.remove(&child);
+    }
+
+    let (path, line) = match result.map(|r| &r.payload) {
+        Some(Payload::File(path) | Payload::Directory(path)) => (path, None),
+        Some(Payload::FileLine { path, line }) => (path, Some(*line)),
+        _ => {
+            preview.hide();
+            return;
+        }
+    };
+    let Ok(metadata) = fs::metadata(path) else {
+        preview.hide();
+        return;
+    };
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| path.to_string_lossy().to_string());
+    let title = Label::new(None);
+    title.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(&name)));
+    title.set_xalign(0.0);
+    title.set_ellipsize(EllipsizeMode::Middle);
+    preview.pack_start(&title, false, false, 0);
+
+    let content_type = if metadata.is_dir() {
+        glib::GString::from("inode/directory")
+    } else {
+        gio::content_type_guess(Some(path), &[]).0
+    };
+
+    let (grid, size) = metadata_grid(&metadata, &content_type);
+    if metadata.is_dir() {
+        let listing = text_label("");
+        preview.pack_start(&listing, false, false, 0);
+        load_listing(&listing, &size, path);
+    } else if let Some(pixbuf) = cached_thumbnail(path, &metadata) {
+        preview.pack_start(&Image::from_pixbuf(Some(&pixbuf)), false, false, 0);
+    } else if gio::content_type_is_a(&content_type, "image/*") && metadata.len() <= MAX_IMAGE_SIZE {
+        let image = Image::new();
+        preview.pack_start(&image, false, false, 0);
+        load_image(&image, path);
+    } else if gio::content_type_is_a(&content_type, "text/plain") {
+        if let Some(markup) = text_head(path, line) {
+            let label = text_label("");
+            label.set_markup(&markup);
+            preview.pack_start(&label, false, false, 0);
+        }
+    } else {
+        let icon = Image::from_gicon(&gio::content_type_get_icon(&content_type), gtk::IconSize::Dialog);
+        icon.set_pixel_size(128);
+        preview.pack_start(&icon, false, false, 0);
+    }
+
+    preview.pack_end(&grid, false, false, 0);
+
+    for child in preview.children() {
+        child.show_all();
+    }
+    preview.show();
+}
+
+fn text_label(text: &str) -> Label {
+    let label = Label::new(Some(text));
+    label.style_context().add_class("preview-text");
+    label.set_xalign(0.0);
+    label.set_yalign(0.0);
+    label
+}
+
+/// The file's first lines, or those around `line` for a content match,
+/// with the matching line in bold. `None` for binary files.
+fn text_head(path: &Path, line: Option<usize>) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let first = line.map_or(1, |line| line.saturating_sub(CONTEXT_BEFORE).max(1));
+
+    let lines: Vec<String> = match line {
+        Some(_) => BufReader::new(file.take(CONTEXT_SCAN_BYTES))
+            .split(b'\n')
+            .skip(first - 1)
+            .take(TEXT_LINES)
+            .map_while(Result::ok)
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+            .collect(),
+        None => {
+            let mut head = Vec::new();
+            file.take(TEXT_HEAD_BYTES).read_to_end(&mut head).ok()?;
+            if head.contains(&0) {
+                return None;
+            }
+            String::from_utf8_lossy(&head).lines().take(TEXT_LINES).map(String::from).collect()
+        }
+    };
+
+    let markup = lines
+        .iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let text = text.replace('\t', "    ").trim_end().to_string();
+            let text = match text.char_indices().nth(TEXT_LINE_CHARS) {
+                Some((end, _)) => format!("{}…", &text[..end]),
+                None => text,
+            };
+            let escaped = glib::markup_escape_text(&text);
+            if Some(first + i) == line {
+                format!("<b>{}</b>", escaped)
+            } else {
+                escaped.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(markup)
+}
+
+/// The first entries of a directory, and how many it has.
+fn dir_listing(path: &Path) -> (String, usize) {
+    let Ok(entries) = fs::read_dir(path) else {
+        return (String::new(), 0);
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                format!("{}/", name)
+            } else {
+                name
+            }
+        })
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+
+    let count = names.len();
+    names.truncate(DIR_ENTRIES);
+    if count > DIR_ENTRIES {
+        names.push(format!("… and {} more", count - DIR_ENTRIES));
+    }
+    (names.join("\n"), count)
+}
+
+/// Looks `path` up in the freedesktop thumbnail cache, ignoring
+/// thumbnails made before the file last changed.
+fn cached_thumbnail(path: &Path, metadata: &Metadata) -> Option<Pixbuf> {
+    let uri = gio::File::for_path(path).uri();
+    let hash = glib::compute_checksum_for_bytes(glib::ChecksumType::Md5, &glib::Bytes::from(uri.as_bytes()))?;
+    let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let cache = dirs::cache_dir()?.join("thumbnails");
+
+    ["x-large", "large", "normal"].iter().find_map(|size| {
+        let thumb = cache.join(size).join(format!("{}.png", hash));
+        let pixbuf = Pixbuf::from_file(&thumb).ok()?;
+        if pixbuf.option("tEXt::Thumb::MTime").is_some_and(|t| t.parse::<u64>() != Ok(mtime)) {
+            return None;
+        }
+        scale_to_fit(pixbuf)
+    })
+}
+
+/// Lists the directory `path` into `listing`, and its entry count into
+/// `size`, off the main loop.
+fn load_listing(listing: &Label, size: &Label, path: &Path) {
+    let (listing, size, path) = (listing.downgrade(), size.downgrade(), path.to_path_buf());
+    let loading = glib::spawn_future_local(async move {
+        let Ok((text, count)) = gio::spawn_blocking(move || dir_listing(&path)).await else {
+            return;
+        };
+        if let (Some(listing), Some(size)) = (listing.upgrade(), size.upgrade()) {
+            let count = format!("{} items", count);
+            listing.set_text(&text);
+            size.set_text(&count);
+            size.set_tooltip_text(Some(&count));
+        }
+    });
+    LOADING.with(|current| current.replace(Some(loading)));
+}
+
+/// Decodes `path` into `image` off the main loop, scaled down to fit the
+/// pane but never up.
+fn load_image(image: &Image, path: &Path) {
+    let (image, path) = (image.downgrade(), path.to_path_buf());
+    let loading = glib::spawn_future_local(async move {
+        let Ok(Some((_, width, height))) = Pixbuf::file_info_future(path.clone()).await else {
+            return;
+        };
+        let size = PREVIEW_WIDTH.min(width.max(height));
+        let Ok(stream) = gio::File::for_path(&path).read_future(glib::Priority::DEFAULT).await else {
+            return;
+        };
+        if let (Ok(pixbuf), Some(image)) = (Pixbuf::from_stream_at_scale_future(&stream, size, size, true).await, image.upgrade()) {
+            image.set_from_pixbuf(Some(&pixbuf));
+        }
+    });
+    LOADING.with(|current| current.replace(Some(loading)));
+}
+
+fn scale_to_fit(pixbuf: Pixbuf) -> Option<Pixbuf> {
+    let (width, height) = (pixbuf.width(), pixbuf.height());
+    if width <= PREVIEW_WIDTH && height <= PREVIEW_WIDTH {
+        return Some(pixbuf);
+    }
+    let scale = PREVIEW_WIDTH as f64 / width.max(height) as f64;
+    pixbuf.scale_simple(
+        ((width as f64 * scale) as i32).max(1),
+        ((height as f64 * scale) as i32).max(1),
+        gtk::gdk_pixbuf::InterpType::Bilinear,
+    )
+}
+
+/// The grid and its size label, left empty for a directory until
+/// `load_listing` counts its entries.
+fn metadata_grid(metadata: &Metadata, content_type: &str) -> (Grid, Label) {
+    let grid = Grid::new();
+    grid.set_row_spacing(2);
+    grid.set_column_spacing(8);
+
+    let size = if metadata.is_dir() {
+        String::new()
+    } else {
+        glib::format_size(metadata.len()).to_string()
+    };
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .and_then(|since| glib::DateTime::from_unix_local(since.as_secs() as i64).ok())
+        .and_then(|time| time.format("%Y-%m-%d %H:%M").ok())
+        .map(|time| time.to_string())
+        .unwrap_or_default();
+    let mime = gio::content_type_get_mime_type(content_type)
+        .map(|mime| mime.to_string())
+        .unwrap_or_default();
+    let description = gio::content_type_get_description(content_type);
+    let mode = metadata.permissions().mode();
+
+    let rows = [
+        ("Size", size),
+        ("Modified", modified),
+        ("Type", format!("{} ({})", description, mime)),
+        ("Permissions", format!("{} ({:o})", permissions_string(mode, metadata.is_dir()), mode & 0o7777)),
+    ];
+    let values: Vec<Label> = rows
+        .iter()
+        .enumerate()
+        .map(|(row, (key, value))| {
+            let key_label = Label::new(Some(key));
+            key_label.style_context().add_class("preview-key");
+            key_label.set_xalign(0.0);
+            let value_label = Label::new(Some(value));
+            value_label.set_xalign(0.0);
+            value_label.set_hexpand(true);
+            value_label.set_ellipsize(EllipsizeMode::End);
+            value_label.set_tooltip_text(Some(value));
+            grid.attach(&key_label, 0, row as i32, 1, 1);
+            grid.attach(&value_label, 1, row as i32, 1, 1);
+            value_label
+        })
+        .collect();
+    // The size row comes first
+    (grid, values[0].clone())
+}
+
+/// `ls -l` style `drwxr-xr-x`.
+fn permissions_string(mode: u32, is_dir: bool) -> String {
+    let mut out = String::from(if is_dir { "d" } else { "-" });
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    out
+}
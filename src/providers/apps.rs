@@ -0,0 +1,151 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::desktop_entry::{expand_exec, load_entries, DesktopEntry};
+use crate::fuzzy::best_match;
+use crate::history;
+use crate::openers::get_openers;
+use super::{sort_results, CancelToken, Payload, ResultAction, ResultIcon, ResultKind, SearchProvider, SearchResult};
+
+pub struct AppsProvider;
+
+impl SearchProvider for AppsProvider {
+    fn id(&self) -> &'static str {
+        "apps"
+    }
+
+    fn name(&self) -> &'static str {
+        "Apps"
+    }
+
+    fn prefixes(&self) -> &'static [&'static str] {
+        &["app:"]
+    }
+
+    fn query(&self, query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+        search_apps(query, cancel)
+    }
+
+    fn activate(&self, result: &SearchResult, _query: &str) {
+        if let Payload::DesktopEntry(path) = &result.payload {
+            launch_desktop_entry(path, &[]);
+        }
+    }
+
+    fn activate_action(&self, _result: &SearchResult, action: &ResultAction, _parent: &gtk::Window) {
+        if let Payload::DesktopAction { entry, action } = &action.payload {
+            launch_desktop_action(entry, action);
+        }
+    }
+}
+
+/// The desktop entries of every app dir, read once per process; parsing
+/// them (and probing `TryExec`) on each keystroke is too slow.
+pub(super) fn app_entries() -> &'static [DesktopEntry] {
+    static ENTRIES: OnceLock<Vec<DesktopEntry>> = OnceLock::new();
+    ENTRIES.get_or_init(|| load_entries(&get_openers().app_dirs))
+}
+
+pub fn search_apps(query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = app_entries()
+        .iter()
+        .take_while(|_| !cancel.is_cancelled())
+        .filter_map(|entry| {
+            let (term, matched) = best_match(query, entry.search_terms())?;
+            let mut result = app_result(entry);
+            result.score = matched.score;
+            match term {
+                0 => result.title_matches = matched.positions,
+                // The generic name, shown as the subtitle when present
+                1 if entry.generic_name.is_some() => result.subtitle_matches = matched.positions,
+                _ => {}
+            }
+            Some(result)
+        })
+        .collect();
+
+    sort_results(&mut results);
+    results
+}
+
+fn app_result(entry: &DesktopEntry) -> SearchResult {
+    let mut result = SearchResult::new(ResultKind::App, entry.name.as_str(), Payload::DesktopEntry(entry.path.clone()));
+    result.subtitle = entry
+        .generic_name
+        .clone()
+        .or_else(|| entry.comment.clone())
+        .unwrap_or_else(|| entry.path.to_string_lossy().to_string());
+    result.icon = entry_icon(entry.icon.as_deref()).unwrap_or_else(|| ResultIcon::Name(String::from("application-x-executable")));
+    result.actions = entry
+        .actions
+        .iter()
+        .map(|action| ResultAction {
+            title: action.name.clone(),
+            icon: entry_icon(action.icon.as_deref()),
+            payload: Payload::DesktopAction {
+                entry: entry.path.clone(),
+                action: action.id.clone(),
+            },
+        })
+        .collect();
+    result
+}
+
+/// `Icon=` holds either a theme icon name or an absolute path.
+pub(super) fn entry_icon(icon: Option<&str>) -> Option<ResultIcon> {
+    match icon {
+        Some(icon) if icon.starts_with('/') => Some(ResultIcon::Path(PathBuf::from(icon))),
+        Some(icon) if !icon.is_empty() => Some(ResultIcon::Name(icon.to_string())),
+        _ => None,
+    }
+}
+
+/// Launches the app described by the `.desktop` file at `path`, handing
+/// it `targets` (paths or URIs) through its `Exec` field codes.
+pub fn launch_desktop_entry(path: &Path, targets: &[String]) {
+    if let Some(entry) = DesktopEntry::from_file(path) {
+        if let Some(exec) = &entry.exec {
+            launch_exec(&entry, exec, targets);
+            history::record(&Payload::DesktopEntry(path.to_path_buf()));
+        }
+    }
+}
+
+/// Launches one of the entry's `[Desktop Action]` groups with its own
+/// `Exec` line.
+pub fn launch_desktop_action(path: &Path, action_id: &str) {
+    if let Some(entry) = DesktopEntry::from_file(path) {
+        if let Some(exec) = entry.action(action_id).and_then(|a| a.exec.as_ref()) {
+            launch_exec(&entry, exec, &[]);
+            history::record(&Payload::DesktopAction {
+                entry: path.to_path_buf(),
+                action: action_id.to_string(),
+            });
+        }
+    }
+}
+
+fn launch_exec(entry: &DesktopEntry, exec: &str, targets: &[String]) {
+    for mut command_line in expand_exec(exec, entry, targets) {
+        if entry.terminal {
+            let terminal = env::var("TERMINAL").unwrap_or_else(|_| String::from("xterm"));
+            command_line.splice(0..0, [terminal, String::from("-e")]);
+        }
+
+        let Some((program, args)) = command_line.split_first() else {
+            continue;
+        };
+
+        let mut command = Command::new(program);
+        command.args(args);
+        if let Some(dir) = &entry.working_dir {
+            command.current_dir(dir);
+        }
+
+        if let Err(e) = command.spawn() {
+            eprintln!("Failed to launch {}: {}", program, e);
+        }
+    }
+}
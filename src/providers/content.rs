@@ -0,0 +1,205 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::watcher::SharedIndex;
+use super::files::open_with_configured_app;
+use super::{CancelToken, Payload, ResultIcon, ResultKind, SearchProvider, SearchResult};
+
+/// Shorter queries match nearly every file.
+const MIN_QUERY_LEN: usize = 3;
+/// Search stops once this many lines have matched.
+const MAX_RESULTS: usize = 100;
+/// Files bigger than this are skipped.
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+/// Matches per file, so one log file can't fill the list.
+const MAX_MATCHES_PER_FILE: usize = 5;
+/// Snippets are cut to about this many chars around the match.
+const SNIPPET_LEN: usize = 120;
+
+/// Searches inside the text files of the file index, like `grep -i` over
+/// the configured roots. Matching is case-insensitive unless the query
+/// has an uppercase letter.
+pub struct ContentProvider {
+    index: SharedIndex,
+}
+
+impl ContentProvider {
+    pub fn new(index: SharedIndex) -> Self {
+        Self { index }
+    }
+}
+
+impl SearchProvider for ContentProvider {
+    fn id(&self) -> &'static str {
+        "content"
+    }
+
+    fn name(&self) -> &'static str {
+        "Content"
+    }
+
+    fn prefixes(&self) -> &'static [&'static str] {
+        &[">", "grep:"]
+    }
+
+    fn query(&self, query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+        if query.chars().count() < MIN_QUERY_LEN {
+            return Vec::new();
+        }
+
+        // Copy the paths out so the index isn't locked for the whole scan
+        let paths: Vec<PathBuf> = loop {
+            if cancel.is_cancelled() {
+                return Vec::new();
+            }
+            if let Ok(index) = self.index.read() {
+                if let Some(index) = index.as_ref() {
+                    break index.files().map(|f| PathBuf::from(&*f.path)).collect();
+                }
+            }
+            thread::sleep(Duration::from_millis(20));
+        };
+
+        search_content(&paths, query, cancel)
+    }
+
+    fn activate(&self, result: &SearchResult, _query: &str) {
+        if let Payload::FileLine { path, line } = &result.payload {
+            open_with_configured_app(&path.to_string_lossy(), Some(*line));
+        }
+    }
+}
+
+/// Scans `paths` on a few threads, each taking the next file off a shared
+/// counter, until `MAX_RESULTS` lines have matched or `cancel` fires.
+pub fn search_content(paths: &[PathBuf], query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let needle = if case_sensitive { query.to_string() } else { query.to_lowercase() };
+
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                if cancel.is_cancelled() || found.load(Ordering::SeqCst) >= MAX_RESULTS {
+                    return;
+                }
+                let Some(path) = paths.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                    return;
+                };
+
+                let matches = search_file(path, &needle, case_sensitive);
+                if matches.is_empty() {
+                    continue;
+                }
+                found.fetch_add(matches.len(), Ordering::SeqCst);
+                if let Ok(mut results) = results.lock() {
+                    results.extend(matches);
+                }
+            });
+        }
+    });
+
+    if cancel.is_cancelled() {
+        return Vec::new();
+    }
+
+    let mut results = results.into_inner().unwrap_or_default();
+    results.sort_by_key(|result: &SearchResult| match &result.payload {
+        Payload::FileLine { path, line } => (path.clone(), *line),
+        _ => (PathBuf::new(), 0),
+    });
+    results.truncate(MAX_RESULTS);
+    // Keep file order through the registry's ranking, frecency aside
+    for (i, result) in results.iter_mut().enumerate() {
+        result.score = -(i as i64);
+    }
+    results
+}
+
+/// Matching lines of one file; nothing for binary or oversized files.
+fn search_file(path: &Path, needle: &str, case_sensitive: bool) -> Vec<SearchResult> {
+    let mut results = Vec::new();
+
+    let Ok(file) = File::open(path) else {
+        return results;
+    };
+    if file.metadata().map(|m| m.len() > MAX_FILE_SIZE).unwrap_or(true) {
+        return results;
+    }
+
+    let mut reader = BufReader::new(file);
+    // A NUL byte early on means a binary file
+    if reader.fill_buf().map(|head| head.contains(&0)).unwrap_or(true) {
+        return results;
+    }
+
+    let mut bytes = Vec::new();
+    if reader.read_to_end(&mut bytes).is_err() {
+        return results;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+
+    for (number, line) in text.lines().enumerate() {
+        let haystack = if case_sensitive { line.to_string() } else { line.to_lowercase() };
+        let Some(start) = haystack.find(needle) else {
+            continue;
+        };
+        // Lowercasing can change byte lengths, so only trust the offset when it didn't
+        let start = (haystack.len() == line.len()).then_some(start);
+
+        results.push(line_result(path, number + 1, line, start, needle.chars().count()));
+        if results.len() >= MAX_MATCHES_PER_FILE {
+            break;
+        }
+    }
+
+    results
+}
+
+fn line_result(path: &Path, line: usize, text: &str, start: Option<usize>, len: usize) -> SearchResult {
+    let (snippet, match_start) = snippet(text, start.unwrap_or(0));
+
+    let mut result = SearchResult::new(
+        ResultKind::File,
+        snippet,
+        Payload::FileLine { path: path.to_path_buf(), line },
+    );
+    if start.is_some() {
+        result.title_matches = (match_start..match_start + len).collect();
+    }
+    result.subtitle = format!("{}:{}", path.display(), line);
+    result.icon = ResultIcon::Name(String::from("text-x-generic"));
+    result
+}
+
+/// The line trimmed and cut down to `SNIPPET_LEN` chars around the match
+/// at byte `start`, and the char index the match starts at within it.
+fn snippet(line: &str, start: usize) -> (String, usize) {
+    let chars: Vec<char> = line.chars().collect();
+    let match_char = line.get(..start).map(|s| s.chars().count()).unwrap_or(0);
+
+    let leading = chars.iter().take_while(|c| c.is_whitespace()).count();
+    let from = match_char.saturating_sub(SNIPPET_LEN / 3).max(leading).min(match_char);
+    let to = (from + SNIPPET_LEN).min(chars.len());
+
+    let mut snippet: String = chars[from..to].iter().collect();
+    let mut offset = match_char - from;
+    if from > leading {
+        snippet.insert(0, '…');
+        offset += 1;
+    }
+    if to < chars.len() {
+        snippet.push('…');
+    }
+
+    (snippet.trim_end().to_string(), offset)
+}
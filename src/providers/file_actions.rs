@@ -0,0 +1,118 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use gio::prelude::FileExt;
+use glib::prelude::ToVariant;
+
+use crate::openers::get_openers;
+use crate::ui::{ask_new_name, copy_file, copy_text};
+use super::files::{open_directory, open_terminal};
+use super::open_with::open_with;
+use super::{FileAction, Payload, ResultAction, ResultIcon};
+
+/// The actions panel of a file or directory result: reveal, terminal,
+/// copying, rename and trash.
+pub fn file_actions(path: &Path, is_dir: bool) -> Vec<ResultAction> {
+    let terminal_dir = if is_dir { Some(path) } else { path.parent() };
+
+    let mut actions = vec![action("Show in Folder", "folder-open", path, FileAction::Reveal)];
+    if let Some(dir) = terminal_dir {
+        actions.push(ResultAction {
+            title: String::from("Open in Terminal"),
+            icon: Some(ResultIcon::Name(String::from("utilities-terminal"))),
+            payload: Payload::Terminal(dir.to_path_buf()),
+        });
+    }
+    actions.extend([
+        action("Copy Path", "edit-copy", path, FileAction::CopyPath),
+        action("Copy URI", "edit-copy", path, FileAction::CopyUri),
+        action(if is_dir { "Copy Folder" } else { "Copy File" }, "edit-copy", path, FileAction::CopyFile),
+        action("Rename…", "document-edit", path, FileAction::Rename),
+        action("Move to Trash", "user-trash", path, FileAction::Trash),
+    ]);
+    actions
+}
+
+fn action(title: &str, icon: &str, path: &Path, action: FileAction) -> ResultAction {
+    ResultAction {
+        title: String::from(title),
+        icon: Some(ResultIcon::Name(String::from(icon))),
+        payload: Payload::FileAction { path: path.to_path_buf(), action },
+    }
+}
+
+/// Runs an action from `file_actions` or `add_open_with`. Called on the
+/// GTK thread, which the clipboard and rename dialog need; `parent` is
+/// the window the dialog belongs to.
+pub fn run_file_action(payload: &Payload, parent: &gtk::Window) {
+    match payload {
+        Payload::Terminal(dir) => open_terminal(dir),
+        Payload::OpenWith { app, file } => open_with(app, file),
+        Payload::FileAction { path, action } => match action {
+            FileAction::Reveal => reveal(path),
+            FileAction::CopyPath => copy_text(&path.to_string_lossy()),
+            FileAction::CopyUri => copy_text(&gio::File::for_path(path).uri()),
+            FileAction::CopyFile => copy_file(path),
+            FileAction::Rename => rename(path, parent),
+            FileAction::Trash => {
+                if let Err(e) = gio::File::for_path(path).trash(None::<&gio::Cancellable>) {
+                    eprintln!("Failed to move {} to the trash: {}", path.display(), e);
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// Shows `path` selected in its folder through the file manager's
+/// `org.freedesktop.FileManager1` D-Bus interface, or just opens the
+/// folder when a `file_manager` is configured or nothing answers.
+fn reveal(path: &Path) {
+    let Some(parent) = path.parent() else {
+        return;
+    };
+    if get_openers().file_manager.is_some() {
+        open_directory(parent);
+        return;
+    }
+
+    let uri = gio::File::for_path(path).uri().to_string();
+    let shown = gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>).and_then(|bus| {
+        bus.call_sync(
+            Some("org.freedesktop.FileManager1"),
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1",
+            "ShowItems",
+            Some(&(vec![uri], "").to_variant()),
+            None,
+            gio::DBusCallFlags::NONE,
+            2000,
+            None::<&gio::Cancellable>,
+        )
+    });
+
+    if shown.is_err() {
+        open_directory(parent);
+    }
+}
+
+fn rename(path: &Path, window: &gtk::Window) {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return;
+    };
+    let Some(new_name) = ask_new_name(window, &name.to_string_lossy()) else {
+        return;
+    };
+    if new_name.contains('/') {
+        eprintln!("Not renaming {}: {:?} is not a file name", path.display(), new_name);
+        return;
+    }
+
+    let target: PathBuf = parent.join(&new_name);
+    if target.exists() {
+        eprintln!("Not renaming {}: {} already exists", path.display(), target.display());
+        return;
+    }
+    if let Err(e) = fs::rename(path, &target) {
+        eprintln!("Failed to rename {}: {}", path.display(), e);
+    }
+}
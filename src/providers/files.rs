@@ -0,0 +1,290 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use gio::{Icon, ThemedIcon, FileIcon, prelude::{AppInfoExt, FileExt}};
+use shell_escape::escape;
+
+use crate::history;
+use crate::index::FileIndex;
+use crate::watcher::{self, SharedIndex};
+use crate::openers::get_openers;
+use super::filters::FileFilters;
+use super::file_actions::{file_actions, run_file_action};
+use super::open_with::add_open_with;
+use super::recent::{merge_recent, search_recent};
+use super::{sort_results, CancelToken, Payload, ResultAction, ResultIcon, ResultKind, SearchProvider, SearchResult};
+
+pub struct FilesProvider {
+    index: SharedIndex,
+}
+
+impl FilesProvider {
+    /// Loads the cached file index in the background right away, so it is
+    /// usually ready by the time the first query comes in, then refreshes
+    /// it against the disk and keeps it in sync while the launcher is
+    /// open. Without a cache the index is built from scratch.
+    pub fn new() -> Self {
+        let index: SharedIndex = Arc::new(RwLock::new(None));
+
+        let loading = index.clone();
+        thread::spawn(move || {
+            let config = get_openers().files;
+            let publish = |index: FileIndex| {
+                if let Ok(mut slot) = loading.write() {
+                    *slot = Some(index);
+                }
+            };
+
+            match FileIndex::load(&config) {
+                Some(cached) => {
+                    let mut refreshed = cached.clone();
+                    publish(cached);
+                    if refreshed.refresh(&config) {
+                        refreshed.save();
+                        publish(refreshed);
+                    }
+                }
+                None => {
+                    let built = FileIndex::build(&config);
+                    built.save();
+                    publish(built);
+                }
+            }
+
+            watcher::run(&loading, &config);
+        });
+
+        Self { index }
+    }
+
+    /// The index, shared with the content search provider.
+    pub fn index(&self) -> SharedIndex {
+        self.index.clone()
+    }
+}
+
+impl SearchProvider for FilesProvider {
+    fn id(&self) -> &'static str {
+        "files"
+    }
+
+    fn name(&self) -> &'static str {
+        "Files"
+    }
+
+    fn prefixes(&self) -> &'static [&'static str] {
+        &["file:"]
+    }
+
+    /// Recent documents lead: alone for an empty query, merged with high
+    /// priority into the index's matches otherwise, filter-only queries
+    /// (`ext:pdf`) included.
+    fn query(&self, query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+        let recent = search_recent(query, cancel);
+        if query.trim().is_empty() && !recent.is_empty() {
+            recent
+        } else {
+            loop {
+                if cancel.is_cancelled() {
+                    return Vec::new();
+                }
+                if let Ok(index) = self.index.read() {
+                    if let Some(index) = index.as_ref() {
+                        break merge_recent(recent, search_files(index, query, cancel));
+                    }
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+    }
+
+    fn activate(&self, result: &SearchResult, _query: &str) {
+        match &result.payload {
+            Payload::File(path) => open_with_configured_app(&path.to_string_lossy(), None),
+            Payload::Directory(path) => open_directory(path),
+            _ => {}
+        }
+    }
+
+    fn expand_actions(&self, result: &mut SearchResult) {
+        add_open_with(result);
+    }
+
+    fn activate_action(&self, _result: &SearchResult, action: &ResultAction, parent: &gtk::Window) {
+        run_file_action(&action.payload, parent);
+    }
+}
+
+pub fn search_files(index: &FileIndex, query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+    let (filters, query) = FileFilters::parse(query);
+    let Some(mut matches) = index.search(&query, cancel) else {
+        return Vec::new();
+    };
+
+    matches.sort_by(|a, b| b.1.score.cmp(&a.1.score).then_with(|| a.0.path.cmp(&b.0.path)));
+
+    let mut results = Vec::new();
+    for (i, (file, matched)) in matches.into_iter().enumerate() {
+        if results.len() >= 200 {
+            break;
+        }
+        // Filters can reject most matches, each costing a stat
+        if i % 1024 == 0 && cancel.is_cancelled() {
+            return Vec::new();
+        }
+        if !filters.matches_path(Path::new(&*file.path), file.is_dir) {
+            continue;
+        }
+
+        // The index may be older than the file system
+        let path = file.path.to_string();
+        match fs::metadata(&path) {
+            Ok(metadata) if filters.matches_metadata(&metadata) => {}
+            _ => continue,
+        }
+
+        let name = file.name().to_string();
+        results.push(file_result(path, name, file.is_dir, matched.positions, matched.score));
+    }
+
+    sort_results(&mut results);
+    results
+}
+
+/// Result for a file or directory whose `name` matched the query at
+/// `positions`.
+pub(super) fn file_result(path: String, name: String, is_dir: bool, positions: Vec<usize>, score: i64) -> SearchResult {
+    // The name is the tail of the path, so the same matches highlight
+    // there too
+    let offset = path.chars().count() - name.chars().count();
+    let mut result = if is_dir {
+        directory_result(name, PathBuf::from(&path))
+    } else {
+        let mut result = SearchResult::new(ResultKind::File, name, Payload::File(PathBuf::from(&path)));
+        result.icon = get_file_icon(&path);
+        result.actions = file_actions(Path::new(&path), false);
+        result
+    };
+    result.score = score;
+    result.subtitle_matches = positions.iter().map(|p| p + offset).collect();
+    result.title_matches = positions;
+    result.subtitle = path;
+    result
+}
+
+fn directory_result(name: String, path: PathBuf) -> SearchResult {
+    let mut result = SearchResult::new(ResultKind::Directory, name, Payload::Directory(path.clone()));
+    result.icon = ResultIcon::Name(String::from("folder"));
+    result.actions = file_actions(&path, true);
+    result
+}
+
+fn get_file_icon(filepath: &str) -> ResultIcon {
+    let guessed = gio::content_type_guess(Some(Path::new(filepath)), &[]);
+    let content_type = guessed.0;
+    let icon = gio::content_type_get_icon(&content_type);
+    icon_to_result_icon(&icon)
+}
+
+pub(super) fn icon_to_result_icon(icon: &Icon) -> ResultIcon {
+    use gio::prelude::Cast;
+
+    if let Some(themed) = icon.downcast_ref::<ThemedIcon>() {
+        if let Some(name) = themed.names().first() {
+            return ResultIcon::Name(name.to_string());
+        }
+    } else if let Some(file_icon) = icon.downcast_ref::<FileIcon>() {
+        if let Some(path) = file_icon.file().path() {
+            return ResultIcon::Path(path);
+        }
+    }
+
+    ResultIcon::Name(String::from("application-x-executable"))
+}
+
+/// Opens `filepath` with the `[openers]` command for it, or else the
+/// default application for its MIME type as set in `mimeapps.list`. With a
+/// `line`, the `editor` command is preferred when the file's opener doesn't
+/// take a `{line}`.
+pub fn open_with_configured_app(filepath: &str, line: Option<usize>) {
+    let path = Path::new(filepath);
+    let content_type = gio::content_type_guess(Some(path), &[]).0;
+    let mime = gio::content_type_get_mime_type(&content_type)
+        .map(|mime| mime.to_string())
+        .unwrap_or_default();
+
+    let openers = get_openers();
+    let mut template = openers.opener_for(path, &mime);
+    if line.is_some() && !template.is_some_and(|cmd| cmd.contains("{line}")) {
+        template = openers.editor.as_ref().or(template);
+    }
+
+    match template {
+        Some(cmd) => {
+            let command = cmd
+                .replace("{file}", &escape(filepath.into()))
+                .replace("{line}", &line.unwrap_or(1).to_string());
+            println!("Launching: {}", command);
+
+            if let Err(e) = Command::new("sh").arg("-c").arg(&command).spawn() {
+                eprintln!("Failed to launch: {}", e);
+                std::process::exit(1);
+            }
+        }
+        None => open_with_default_app(path, &content_type),
+    }
+
+    history::record(&Payload::File(PathBuf::from(filepath)));
+}
+
+/// Launches the default application for `content_type` on `path`, falling
+/// back to whatever handles its URI.
+fn open_with_default_app(path: &Path, content_type: &str) {
+    let file = gio::File::for_path(path);
+
+    let launched = match gio::AppInfo::default_for_type(content_type, false) {
+        Some(app) => app.launch(std::slice::from_ref(&file), None::<&gio::AppLaunchContext>),
+        None => gio::AppInfo::launch_default_for_uri(&file.uri(), None::<&gio::AppLaunchContext>),
+    };
+
+    if let Err(e) = launched {
+        eprintln!("Failed to open {}: {}", path.display(), e);
+    }
+}
+
+/// Opens `dir` in the configured file manager.
+pub fn open_directory(dir: &Path) {
+    match get_openers().file_manager {
+        Some(template) => {
+            let command = template.replace("{dir}", &escape(dir.to_string_lossy()));
+            if let Err(e) = Command::new("sh").arg("-c").arg(&command).spawn() {
+                eprintln!("Failed to open {}: {}", dir.display(), e);
+            }
+        }
+        None => open_with_default_app(dir, "inode/directory"),
+    }
+
+    history::record(&Payload::Directory(dir.to_path_buf()));
+}
+
+/// Opens the configured terminal in `dir`.
+pub fn open_terminal(dir: &Path) {
+    let mut command = match get_openers().terminal {
+        Some(template) => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(template.replace("{dir}", &escape(dir.to_string_lossy())));
+            command
+        }
+        None => Command::new(env::var("TERMINAL").unwrap_or_else(|_| String::from("xterm"))),
+    };
+
+    if let Err(e) = command.current_dir(dir).spawn() {
+        eprintln!("Failed to open a terminal in {}: {}", dir.display(), e);
+    }
+
+    history::record(&Payload::Terminal(dir.to_path_buf()));
+}
@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// Inline filters narrowing a file search, e.g.
+/// `report ext:pdf modified:<7d size:>1M type:image`.
+///
+/// - `ext:pdf` or `ext:jpg,png` — file extension
+/// - `size:>1M`, `size:<=500k` — size in bytes, with `k`, `M`, `G` units;
+///   no comparison means at least that size
+/// - `modified:<7d` — changed within the last 7 days (`>` for longer
+///   ago), with `h`, `d`, `w`, `m` (30 days) and `y` units
+/// - `type:image` — `image`, `video`, `audio`, `text`, `document`,
+///   `archive`, `dir` or `file`, or any MIME type prefix
+#[derive(Debug, Default)]
+pub struct FileFilters {
+    exts: Vec<String>,
+    size: Option<(Ordering, bool, u64)>,
+    modified: Option<(Ordering, bool, Duration)>,
+    kind: Option<String>,
+}
+
+impl FileFilters {
+    /// Splits the filters off `query`, returning them and the words left
+    /// to fuzzy-match. Tokens that don't parse stay in the query.
+    pub fn parse(query: &str) -> (Self, String) {
+        let mut filters = Self::default();
+        let mut rest = Vec::new();
+
+        for token in query.split_whitespace() {
+            let parsed = match token.split_once(':') {
+                Some(("ext", value)) if !value.is_empty() => {
+                    filters.exts.extend(
+                        value
+                            .split(',')
+                            .filter(|e| !e.is_empty())
+                            .map(|e| e.trim_start_matches('.').to_lowercase()),
+                    );
+                    true
+                }
+                Some(("size", value)) => parse_comparison(value, parse_size)
+                    .map(|size| filters.size = Some(size))
+                    .is_some(),
+                Some(("modified", value)) => parse_comparison(value, parse_age)
+                    .map(|age| filters.modified = Some(age))
+                    .is_some(),
+                Some(("type", value)) if !value.is_empty() => {
+                    filters.kind = Some(value.to_lowercase());
+                    true
+                }
+                _ => false,
+            };
+
+            if !parsed {
+                rest.push(token);
+            }
+        }
+
+        (filters, rest.join(" "))
+    }
+
+    /// Whether the path passes the filters that need no metadata.
+    pub fn matches_path(&self, path: &Path, is_dir: bool) -> bool {
+        if !self.exts.is_empty() {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+            if is_dir || !self.exts.contains(&ext) {
+                return false;
+            }
+        }
+
+        match self.kind.as_deref() {
+            None => true,
+            Some("dir" | "directory" | "folder") => is_dir,
+            Some("file") => !is_dir,
+            Some(_) if is_dir => false,
+            Some(kind) => matches_kind(path, kind),
+        }
+    }
+
+    pub fn matches_metadata(&self, metadata: &Metadata) -> bool {
+        if let Some((ordering, or_equal, size)) = self.size {
+            if metadata.is_dir() || !compare(metadata.len().cmp(&size), ordering, or_equal) {
+                return false;
+            }
+        }
+
+        if let Some((ordering, or_equal, age)) = self.modified {
+            let Some(modified_age) = metadata.modified().ok().and_then(|t| SystemTime::now().duration_since(t).ok()) else {
+                return false;
+            };
+            if !compare(modified_age.cmp(&age), ordering, or_equal) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+fn matches_kind(path: &Path, kind: &str) -> bool {
+    let Some(mime) = mime_guess::from_path(path).first() else {
+        return false;
+    };
+    let essence = mime.essence_str();
+
+    match kind {
+        "image" | "video" | "audio" | "text" => mime.type_() == kind,
+        "document" => {
+            essence == "application/pdf"
+                || ["msword", "opendocument", "officedocument", "rtf", "epub"]
+                    .iter()
+                    .any(|part| essence.contains(part))
+        }
+        "archive" => ["zip", "tar", "gzip", "7z", "rar", "xz", "bzip", "zstd"]
+            .iter()
+            .any(|part| mime.subtype().as_str().contains(part)),
+        _ => essence.starts_with(kind),
+    }
+}
+
+/// `>`, `>=`, `<`, `<=` or `=` followed by a value. A bare value means
+/// at least that much.
+fn parse_comparison<T>(value: &str, parse: fn(&str) -> Option<T>) -> Option<(Ordering, bool, T)> {
+    let (ordering, or_equal, value) = if let Some(v) = value.strip_prefix(">=") {
+        (Ordering::Greater, true, v)
+    } else if let Some(v) = value.strip_prefix("<=") {
+        (Ordering::Less, true, v)
+    } else if let Some(v) = value.strip_prefix('>') {
+        (Ordering::Greater, false, v)
+    } else if let Some(v) = value.strip_prefix('<') {
+        (Ordering::Less, false, v)
+    } else if let Some(v) = value.strip_prefix('=') {
+        (Ordering::Equal, true, v)
+    } else {
+        (Ordering::Greater, true, value)
+    };
+
+    parse(value).map(|parsed| (ordering, or_equal, parsed))
+}
+
+fn compare(actual: Ordering, wanted: Ordering, or_equal: bool) -> bool {
+    actual == wanted || (or_equal && actual == Ordering::Equal)
+}
+
+/// `1M`, `500k`, `2GB`, `100` (bytes).
+fn parse_size(value: &str) -> Option<u64> {
+    let (number, unit) = split_unit(value)?;
+    let multiplier: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+
+/// `12h`, `7d`, `2w`, `3m`, `1y`.
+fn parse_age(value: &str) -> Option<Duration> {
+    let (number, unit) = split_unit(value)?;
+    let seconds: u64 = match unit.to_lowercase().as_str() {
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 7 * 86_400,
+        "m" => 30 * 86_400,
+        "y" => 365 * 86_400,
+        _ => return None,
+    };
+    // Absurd values (`600000000000y`) don't fit a Duration
+    Duration::try_from_secs_f64(number * seconds as f64).ok()
+}
+
+fn split_unit(value: &str) -> Option<(f64, &str)> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let number = value[..split].parse::<f64>().ok()?;
+    Some((number, &value[split..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86_400;
+
+    #[test]
+    fn parse_splits_filters_from_words() {
+        let (filters, rest) = FileFilters::parse("report ext:pdf,.PNG modified:<7d q3 size:>1M type:Image");
+        assert_eq!(rest, "report q3");
+        assert_eq!(filters.exts, ["pdf", "png"]);
+        assert_eq!(filters.size, Some((Ordering::Greater, false, 1 << 20)));
+        assert_eq!(filters.modified, Some((Ordering::Less, false, Duration::from_secs(7 * DAY))));
+        assert_eq!(filters.kind.as_deref(), Some("image"));
+    }
+
+    #[test]
+    fn parse_keeps_tokens_that_are_not_filters() {
+        let query = "ext: size:huge modified:<7x type: notes:todo http://example.com";
+        let (filters, rest) = FileFilters::parse(query);
+        assert_eq!(rest, query);
+        assert!(filters.exts.is_empty() && filters.size.is_none() && filters.modified.is_none() && filters.kind.is_none());
+    }
+
+    #[test]
+    fn parse_keeps_ages_too_large_for_a_duration() {
+        let (filters, rest) = FileFilters::parse("report modified:<600000000000y");
+        assert_eq!(rest, "report modified:<600000000000y");
+        assert!(filters.modified.is_none());
+    }
+
+    #[test]
+    fn comparisons() {
+        assert_eq!(parse_comparison("1M", parse_size), Some((Ordering::Greater, true, 1 << 20)));
+        assert_eq!(parse_comparison(">=1k", parse_size), Some((Ordering::Greater, true, 1024)));
+        assert_eq!(parse_comparison("<=1k", parse_size), Some((Ordering::Less, true, 1024)));
+        assert_eq!(parse_comparison("=10", parse_size), Some((Ordering::Equal, true, 10)));
+        assert_eq!(parse_comparison(">", parse_size), None);
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("100"), Some(100));
+        assert_eq!(parse_size("100b"), Some(100));
+        assert_eq!(parse_size("500k"), Some(500 * 1024));
+        assert_eq!(parse_size("1.5K"), Some(1536));
+        assert_eq!(parse_size("1M"), Some(1 << 20));
+        assert_eq!(parse_size("2GB"), Some(2 << 30));
+        assert_eq!(parse_size("1T"), None);
+        assert_eq!(parse_size("M"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(parse_age("12h"), Some(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_age("7d"), Some(Duration::from_secs(7 * DAY)));
+        assert_eq!(parse_age("1.5d"), Some(Duration::from_secs(DAY * 3 / 2)));
+        assert_eq!(parse_age("2w"), Some(Duration::from_secs(14 * DAY)));
+        assert_eq!(parse_age("3m"), Some(Duration::from_secs(90 * DAY)));
+        assert_eq!(parse_age("1Y"), Some(Duration::from_secs(365 * DAY)));
+        assert_eq!(parse_age("7"), None);
+        assert_eq!(parse_age("600000000000y"), None);
+        assert_eq!(parse_age(&"9".repeat(400)), None);
+    }
+
+    #[test]
+    fn extension_filter() {
+        let (filters, _) = FileFilters::parse("ext:pdf,png");
+        assert!(filters.matches_path(Path::new("/docs/Report.PDF"), false));
+        assert!(filters.matches_path(Path::new("/pics/a.png"), false));
+        assert!(!filters.matches_path(Path::new("/docs/report.txt"), false));
+        assert!(!filters.matches_path(Path::new("/docs/README"), false));
+        assert!(!filters.matches_path(Path::new("/docs/archive.pdf"), true));
+    }
+
+    #[test]
+    fn type_filter() {
+        let matches = |kind: &str, path: &str, is_dir: bool| {
+            FileFilters::parse(&format!("type:{}", kind)).0.matches_path(Path::new(path), is_dir)
+        };
+        assert!(matches("dir", "/home/docs", true));
+        assert!(!matches("dir", "/home/a.txt", false));
+        assert!(matches("file", "/home/a.txt", false));
+        assert!(!matches("file", "/home/docs", true));
+        assert!(matches("image", "/pics/a.jpg", false));
+        assert!(!matches("image", "/pics/a.txt", false));
+        assert!(!matches("image", "/pics.png", true));
+        assert!(matches("document", "/docs/a.pdf", false));
+        assert!(matches("document", "/docs/a.odt", false));
+        assert!(matches("archive", "/dl/a.tar.gz", false));
+        assert!(matches("archive", "/dl/a.zip", false));
+        assert!(matches("application/pdf", "/docs/a.pdf", false));
+        assert!(!matches("video", "/docs/a", false));
+    }
+
+    #[test]
+    fn no_filters_match_everything() {
+        let (filters, rest) = FileFilters::parse("just words");
+        assert_eq!(rest, "just words");
+        assert!(filters.matches_path(Path::new("/anything"), false));
+        assert!(filters.matches_path(Path::new("/anywhere"), true));
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::history::History;
+use crate::openers::{get_openers, WebConfig};
+
+pub mod apps;
+pub mod content;
+mod file_actions;
+pub mod files;
+mod filters;
+mod open_with;
+pub mod recent;
+pub mod web;
+mod result;
+
+pub use result::{FileAction, Payload, ResultAction, ResultIcon, ResultKind, SearchResult};
+
+/// Lets a running query notice that a newer one has superseded it, so
+/// slow providers can stop early.
+#[derive(Clone)]
+pub struct CancelToken {
+    latest: Arc<AtomicU64>,
+    generation: u64,
+}
+
+impl CancelToken {
+    pub fn new(latest: Arc<AtomicU64>, generation: u64) -> Self {
+        Self { latest, generation }
+    }
+
+    /// A token that is never cancelled, for one-off searches.
+    pub fn never() -> Self {
+        Self::new(Arc::new(AtomicU64::new(0)), 0)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.latest.load(Ordering::SeqCst) != self.generation
+    }
+}
+
+/// Providers are queried on the search worker thread and activated on the
+/// GTK thread, hence `Send + Sync`.
+pub trait SearchProvider: Send + Sync {
+    /// Stable identifier, used for config keys and logging.
+    fn id(&self) -> &'static str;
+
+    /// Name shown in the mode label.
+    fn name(&self) -> &'static str;
+
+    /// Typing one of these at the start of the entry routes the query to
+    /// this provider regardless of the active mode.
+    fn prefixes(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn query(&self, query: &str, cancel: &CancelToken) -> Vec<SearchResult>;
+
+    fn activate(&self, result: &SearchResult, query: &str);
+
+    /// Adds actions too costly to work out for every result, just before
+    /// the actions view of `result` opens. Called on the GTK thread.
+    fn expand_actions(&self, _result: &mut SearchResult) {}
+
+    /// Runs one of `result.actions`, picked from the actions view.
+    /// `parent` is the launcher window, for dialogs the action opens.
+    fn activate_action(&self, _result: &SearchResult, _action: &ResultAction, _parent: &gtk::Window) {}
+}
+
+/// Orders results best match first, alphabetically among equal scores.
+pub fn sort_results(results: &mut [SearchResult]) {
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.title.to_lowercase().cmp(&b.title.to_lowercase()))
+    });
+}
+
+pub struct ProviderRegistry {
+    providers: Vec<Box<dyn SearchProvider>>,
+    active: AtomicUsize,
+    history: History,
+    /// The `[web]` engines, read once for the web provider and for
+    /// spotting `!bangs` on every keystroke.
+    web: Arc<WebConfig>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self {
+            providers: Vec::new(),
+            active: AtomicUsize::new(0),
+            history: History::load(),
+            web: Arc::new(get_openers().web),
+        }
+    }
+
+    /// Registry with the built-in providers, in Tab order.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        let files = files::FilesProvider::new();
+        let content = content::ContentProvider::new(files.index());
+        registry.register(Box::new(apps::AppsProvider));
+        registry.register(Box::new(files));
+        registry.register(Box::new(content));
+        registry.register(Box::new(recent::RecentProvider));
+        registry.register(Box::new(web::WebProvider::new(registry.web.clone())));
+        registry
+    }
+
+    pub fn register(&mut self, provider: Box<dyn SearchProvider>) {
+        self.providers.push(provider);
+    }
+
+    pub fn active(&self) -> &dyn SearchProvider {
+        self.providers[self.active.load(Ordering::SeqCst)].as_ref()
+    }
+
+    /// Moves to the next provider (Tab) and returns it.
+    pub fn cycle(&self) -> &dyn SearchProvider {
+        let next = (self.active.load(Ordering::SeqCst) + 1) % self.providers.len();
+        self.active.store(next, Ordering::SeqCst);
+        self.active()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn SearchProvider> {
+        self.providers.iter().find(|p| p.id() == id).map(|p| p.as_ref())
+    }
+
+    /// Picks the provider for the entry text: a matching prefix wins, then
+    /// a web `!bang`, otherwise the active provider. Returns the query with
+    /// the prefix stripped.
+    pub fn resolve<'a>(&self, text: &'a str) -> (&dyn SearchProvider, &'a str) {
+        for provider in &self.providers {
+            for prefix in provider.prefixes() {
+                if let Some(rest) = text.strip_prefix(prefix) {
+                    return (provider.as_ref(), rest.trim_start());
+                }
+            }
+        }
+        if let Some(web) = self.get(web::WebProvider::ID).filter(|_| self.bang_target(text).is_some()) {
+            return (web, text);
+        }
+        (self.active(), text)
+    }
+
+    /// Runs the entry text through the provider it resolves to (or the
+    /// fallback when that finds nothing) and ranks the results by match
+    /// score blended with launch frecency. Returns the id of the provider
+    /// that produced them and the query it was given.
+    pub fn search<'a>(&self, text: &'a str, cancel: &CancelToken) -> (&'static str, &'a str, Vec<SearchResult>) {
+        let (provider, query) = self.resolve(text);
+        let mut owner = provider.id();
+        let mut results = provider.query(query, cancel);
+
+        if results.is_empty() && !cancel.is_cancelled() {
+            if let Some(fallback) = self.fallback() {
+                owner = fallback.id();
+                results = fallback.query(query, cancel);
+            }
+        }
+
+        self.history.boost(&mut results);
+        sort_results(&mut results);
+        (owner, query, results)
+    }
+
+    /// Name of the web engine a `!bang` in `text` sends the search to.
+    pub fn bang_target(&self, text: &str) -> Option<&str> {
+        web::bang_target(&self.web, text)
+    }
+
+    /// Provider used when the resolved one has nothing to offer.
+    pub fn fallback(&self) -> Option<&dyn SearchProvider> {
+        self.get(web::WebProvider::ID)
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use gio::prelude::{AppInfoExt, Cast};
+
+use crate::desktop_entry::DesktopEntry;
+use crate::history;
+use super::apps::{app_entries, entry_icon, launch_desktop_entry};
+use super::files::icon_to_result_icon;
+use super::{Payload, ResultAction, ResultIcon, SearchResult};
+
+/// An application able to open some content type.
+struct App {
+    name: String,
+    icon: Option<ResultIcon>,
+    /// Its `.desktop` file.
+    path: PathBuf,
+}
+
+/// Gives a file result an "Open with …" action per application that can
+/// open it: the default one first, then the others gio knows for the type,
+/// then any desktop entry listing the type in `MimeType=`. Done when its
+/// actions are opened rather than per query, and only once per result.
+pub fn add_open_with(result: &mut SearchResult) {
+    let Payload::File(file) = &result.payload else {
+        return;
+    };
+    if result.actions.iter().any(|action| matches!(action.payload, Payload::OpenWith { .. })) {
+        return;
+    }
+
+    let content_type = gio::content_type_guess(Some(file.as_path()), &[]).0;
+    let actions: Vec<ResultAction> = apps_for_type(&content_type, app_entries())
+        .into_iter()
+        .map(|app| ResultAction {
+            title: format!("Open with {}", app.name),
+            icon: app.icon,
+            payload: Payload::OpenWith { app: app.path, file: file.clone() },
+        })
+        .collect();
+    result.actions.extend(actions);
+}
+
+fn apps_for_type(content_type: &str, entries: &[DesktopEntry]) -> Vec<App> {
+    let mut apps = Vec::new();
+    let mut seen = HashSet::new();
+
+    let registered = gio::AppInfo::default_for_type(content_type, false)
+        .into_iter()
+        .chain(gio::AppInfo::all_for_type(content_type));
+    for info in registered {
+        let Some(id) = info.id().map(|id| id.to_string()) else {
+            continue;
+        };
+        if !seen.insert(id.clone()) {
+            continue;
+        }
+
+        match entries.iter().find(|entry| entry.id == id) {
+            Some(entry) => apps.extend(entry_app(entry)),
+            None => {
+                let Some(path) = info.downcast_ref::<gio::DesktopAppInfo>().and_then(|d| d.filename()) else {
+                    continue;
+                };
+                apps.push(App {
+                    name: info.display_name().to_string(),
+                    icon: info.icon().map(|icon| icon_to_result_icon(&icon)),
+                    path,
+                });
+            }
+        }
+    }
+
+    for entry in entries {
+        let handles = entry
+            .mime_types
+            .iter()
+            .any(|mime| gio::content_type_is_a(content_type, mime));
+        if handles && seen.insert(entry.id.clone()) {
+            apps.extend(entry_app(entry));
+        }
+    }
+
+    apps
+}
+
+fn entry_app(entry: &DesktopEntry) -> Option<App> {
+    entry.exec.as_ref()?;
+    Some(App {
+        name: entry.name.clone(),
+        icon: entry_icon(entry.icon.as_deref()),
+        path: entry.path.clone(),
+    })
+}
+
+/// Launches the application of the `.desktop` file `app` on `file`,
+/// expanding its `%f`/`%u` field codes.
+pub fn open_with(app: &Path, file: &Path) {
+    launch_desktop_entry(app, &[file.to_string_lossy().to_string()]);
+    history::record(&Payload::File(file.to_path_buf()));
+}
@@ -0,0 +1,172 @@
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use gio::prelude::FileExt;
+
+use crate::fuzzy::fuzzy_match;
+use super::files::{file_result, open_with_configured_app};
+use super::filters::FileFilters;
+use super::file_actions::run_file_action;
+use super::open_with::add_open_with;
+use super::{sort_results, CancelToken, Payload, ResultAction, SearchProvider, SearchResult};
+
+/// Recent documents shown at most.
+const MAX_RECENT: usize = 50;
+/// Added to a recent document's score so it ranks above other files
+/// matching as well, or matching filters alone.
+const RECENT_BONUS: i64 = 40;
+
+/// A document from `recently-used.xbel`.
+#[derive(Debug, Clone)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    /// The application that last opened it.
+    pub app: Option<String>,
+    /// Unix time it was last used.
+    pub modified: i64,
+}
+
+/// Documents GTK applications recorded as recently used.
+pub struct RecentProvider;
+
+impl SearchProvider for RecentProvider {
+    fn id(&self) -> &'static str {
+        "recent"
+    }
+
+    fn name(&self) -> &'static str {
+        "Recent"
+    }
+
+    fn prefixes(&self) -> &'static [&'static str] {
+        &["recent:"]
+    }
+
+    fn query(&self, query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+        search_recent(query, cancel)
+    }
+
+    fn activate(&self, result: &SearchResult, _query: &str) {
+        if let Payload::File(path) = &result.payload {
+            open_with_configured_app(&path.to_string_lossy(), None);
+        }
+    }
+
+    fn expand_actions(&self, result: &mut SearchResult) {
+        add_open_with(result);
+    }
+
+    fn activate_action(&self, _result: &SearchResult, action: &ResultAction, parent: &gtk::Window) {
+        run_file_action(&action.payload, parent);
+    }
+}
+
+/// Recent documents matching `query`, file filters included. With no words
+/// to match they come newest first.
+pub fn search_recent(query: &str, cancel: &CancelToken) -> Vec<SearchResult> {
+    let (filters, query) = FileFilters::parse(query);
+    let mut results = Vec::new();
+
+    for (rank, recent) in recent_files().iter().enumerate() {
+        if cancel.is_cancelled() {
+            return Vec::new();
+        }
+        if !filters.matches_path(&recent.path, false) {
+            continue;
+        }
+        match fs::metadata(&recent.path) {
+            Ok(metadata) if metadata.is_file() && filters.matches_metadata(&metadata) => {}
+            _ => continue,
+        }
+
+        let Some(name) = recent.path.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let (score, positions) = if query.is_empty() {
+            (RECENT_BONUS - rank as i64, Vec::new())
+        } else {
+            match fuzzy_match(&query, &name) {
+                Some(matched) => (matched.score + RECENT_BONUS, matched.positions),
+                None => continue,
+            }
+        };
+
+        let mut result = file_result(recent.path.to_string_lossy().to_string(), name, false, positions, score);
+        if let Some(app) = &recent.app {
+            result.subtitle = format!("{} · {}", result.subtitle, app);
+        }
+        results.push(result);
+    }
+
+    sort_results(&mut results);
+    results
+}
+
+/// Puts `recent` ahead of the same files in `results`, which are dropped.
+pub fn merge_recent(recent: Vec<SearchResult>, results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let seen: HashSet<Payload> = recent.iter().map(|r| r.payload.clone()).collect();
+    let mut merged = recent;
+    merged.extend(results.into_iter().filter(|r| !seen.contains(&r.payload)));
+    sort_results(&mut merged);
+    merged
+}
+
+/// The recent documents, read once per process rather than parsing the
+/// XBEL file on each keystroke.
+fn recent_files() -> &'static [RecentFile] {
+    static RECENT: OnceLock<Vec<RecentFile>> = OnceLock::new();
+    RECENT.get_or_init(load_recent)
+}
+
+/// Local files from `$XDG_DATA_HOME/recently-used.xbel`, most recently
+/// used first.
+pub fn load_recent() -> Vec<RecentFile> {
+    let Some(path) = dirs::data_dir().map(|dir| dir.join("recently-used.xbel")) else {
+        return Vec::new();
+    };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let document = match roxmltree::Document::parse(&contents) {
+        Ok(document) => document,
+        Err(e) => {
+            eprintln!("Failed to parse {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+
+    let mut recent: Vec<RecentFile> = document
+        .descendants()
+        .filter(|node| node.has_tag_name("bookmark"))
+        .filter_map(|bookmark| {
+            let path = gio::File::for_uri(bookmark.attribute("href")?).path()?;
+
+            // The application that used it last
+            let app = bookmark
+                .descendants()
+                .filter(|node| node.tag_name().name() == "application")
+                .max_by_key(|node| node.attribute("modified").and_then(parse_time).unwrap_or(0))
+                .and_then(|node| node.attribute("name"))
+                .map(String::from);
+
+            let modified = ["visited", "modified", "added"]
+                .iter()
+                .filter_map(|key| bookmark.attribute(*key).and_then(parse_time))
+                .max()
+                .unwrap_or(0);
+
+            Some(RecentFile { path, app, modified })
+        })
+        .collect();
+
+    recent.sort_by_key(|r| Reverse(r.modified));
+    recent.truncate(MAX_RECENT);
+    recent
+}
+
+/// ISO 8601 timestamp to Unix time.
+fn parse_time(value: &str) -> Option<i64> {
+    glib::DateTime::from_iso8601(value, None).ok().map(|t| t.to_unix())
+}
@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+use serde::Serialize;
+
+/// Icon reference; turned into a widget by the UI on the GTK thread.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultIcon {
+    Name(String),
+    Path(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultKind {
+    App,
+    File,
+    Directory,
+    Web,
+}
+
+/// What activating a result (or one of its actions) acts on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Payload {
+    DesktopEntry(PathBuf),
+    DesktopAction { entry: PathBuf, action: String },
+    File(PathBuf),
+    /// A line in a file, from content search; 1-based.
+    FileLine { path: PathBuf, line: usize },
+    /// `file` opened in the application of the `.desktop` file `app`.
+    OpenWith { app: PathBuf, file: PathBuf },
+    Directory(PathBuf),
+    /// A terminal opened in a directory.
+    Terminal(PathBuf),
+    FileAction { path: PathBuf, action: FileAction },
+    /// `query` searched with the `[web]` engine of id `engine`.
+    WebSearch { engine: String, query: String },
+    /// A URL typed in, opened as is.
+    Url(String),
+}
+
+/// Things done to a file or directory from its actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    /// Show it in its folder.
+    Reveal,
+    CopyPath,
+    CopyUri,
+    /// Copy the file itself, for pasting in a file manager.
+    CopyFile,
+    Rename,
+    Trash,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResultAction {
+    pub title: String,
+    pub icon: Option<ResultIcon>,
+    pub payload: Payload,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub subtitle: String,
+    /// Char indices in `title` / `subtitle` the query matched, for highlighting.
+    pub title_matches: Vec<usize>,
+    pub subtitle_matches: Vec<usize>,
+    pub icon: ResultIcon,
+    pub score: i64,
+    pub kind: ResultKind,
+    pub payload: Payload,
+    pub actions: Vec<ResultAction>,
+}
+
+impl SearchResult {
+    pub fn new(kind: ResultKind, title: impl Into<String>, payload: Payload) -> Self {
+        Self {
+            title: title.into(),
+            subtitle: String::new(),
+            title_matches: Vec::new(),
+            subtitle_matches: Vec::new(),
+            icon: ResultIcon::Name(String::from("application-x-executable")),
+            score: 0,
+            kind,
+            payload,
+            actions: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::sync::Arc;
+use gio::prelude::AppInfoExt;
+
+use crate::history;
+use crate::openers::{WebConfig, WebEngine};
+use super::{CancelToken, Payload, ResultIcon, ResultKind, SearchProvider, SearchResult};
+
+pub struct WebProvider {
+    web: Arc<WebConfig>,
+}
+
+impl WebProvider {
+    pub const ID: &'static str = "web";
+
+    pub fn new(web: Arc<WebConfig>) -> Self {
+        Self { web }
+    }
+}
+
+impl SearchProvider for WebProvider {
+    fn id(&self) -> &'static str {
+        Self::ID
+    }
+
+    fn name(&self) -> &'static str {
+        "Web"
+    }
+
+    fn prefixes(&self) -> &'static [&'static str] {
+        &["?"]
+    }
+
+    fn query(&self, query: &str, _cancel: &CancelToken) -> Vec<SearchResult> {
+        let web = &self.web;
+        let mut results = Vec::new();
+        let (engine, terms) = match split_bang(web, query) {
+            Some((engine, terms)) => (engine, terms),
+            None => {
+                // Offered alongside the search rather than instead of it,
+                // and only above it when clearly meant as a URL: `next.js`
+                // is a file name as much as a domain
+                if let Some(url) = as_url(query.trim()) {
+                    let mut result = SearchResult::new(ResultKind::Web, query.trim(), Payload::Url(url));
+                    result.subtitle = String::from("Open in browser");
+                    result.icon = ResultIcon::Name(String::from("web-browser-symbolic"));
+                    result.score = if clearly_url(query.trim()) { 1 } else { -1 };
+                    results.push(result);
+                }
+                match web.default_engine() {
+                    Some(engine) => (engine, query.trim().to_string()),
+                    None => return results,
+                }
+            }
+        };
+        if terms.is_empty() {
+            return results;
+        }
+
+        let payload = Payload::WebSearch { engine: engine.id.clone(), query: terms.clone() };
+        let mut result = SearchResult::new(ResultKind::Web, &terms, payload);
+        result.subtitle = format!("Search {}", engine.name);
+        result.icon = ResultIcon::Name(String::from("system-search-symbolic"));
+        results.push(result);
+        results
+    }
+
+    fn activate(&self, result: &SearchResult, _query: &str) {
+        match &result.payload {
+            Payload::WebSearch { engine, query } => web_search(&self.web, engine, query),
+            Payload::Url(url) => {
+                open_url(url);
+                history::record(&result.payload);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Finds a known `!bang` as the first or last word of `text`, returning
+/// its engine and the remaining words.
+pub fn split_bang<'a>(web: &'a WebConfig, text: &str) -> Option<(&'a WebEngine, String)> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let engine_at = |i: usize| words.get(i)?.strip_prefix('!').and_then(|bang| web.by_bang(bang));
+
+    let (engine, i) = match engine_at(0) {
+        Some(engine) => (engine, 0),
+        None => (engine_at(words.len().checked_sub(1)?)?, words.len() - 1),
+    };
+    let rest: Vec<&str> = words.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, word)| *word).collect();
+    Some((engine, rest.join(" ")))
+}
+
+/// Name of the engine a `!bang` in `text` sends the search to.
+pub fn bang_target<'a>(web: &'a WebConfig, text: &str) -> Option<&'a str> {
+    split_bang(web, text).map(|(engine, _)| engine.name.as_str())
+}
+
+/// Searches `query` with the engine `engine`, or the default one when
+/// that's no longer configured.
+pub fn web_search(web: &WebConfig, engine: &str, query: &str) {
+    let Some(target) = web.engine(engine).or(web.default_engine()) else {
+        return;
+    };
+    let url = target.url.replace("{query}", &glib::uri_escape_string(query, None::<&str>, false));
+    open_url(&url);
+    history::record(&Payload::WebSearch { engine: engine.to_string(), query: query.to_string() });
+}
+
+/// Top-level domains common enough that `name.tld` is taken for a site
+/// rather than a file name.
+const KNOWN_TLDS: &[&str] = &[
+    "com", "org", "net", "edu", "gov", "io", "dev", "app", "co", "info", "xyz", "eu", "uk", "de", "fr", "nl", "ca", "au",
+    "jp", "ch", "se", "it", "es", "ru", "us",
+];
+
+/// Whether `text`, taken by `as_url`, has a scheme, path, port or common
+/// TLD, or is `localhost`.
+fn clearly_url(text: &str) -> bool {
+    if text.contains("://") || text.contains(['/', '?', '#', ':']) {
+        return true;
+    }
+    text == "localhost" || text.rsplit('.').next().is_some_and(|tld| KNOWN_TLDS.contains(&tld.to_lowercase().as_str()))
+}
+
+/// Opens `url` in the default browser, the handler of its scheme in
+/// `mimeapps.list`, without going through a shell.
+fn open_url(url: &str) {
+    let scheme = glib::uri_parse_scheme(url).unwrap_or_else(|| glib::GString::from("https"));
+    let launched = match gio::AppInfo::default_for_uri_scheme(&scheme) {
+        Some(app) => app.launch_uris(&[url], None::<&gio::AppLaunchContext>),
+        None => gio::AppInfo::launch_default_for_uri(url, None::<&gio::AppLaunchContext>),
+    };
+
+    if let Err(e) = launched {
+        eprintln!("Failed to open {}: {}", url, e);
+    }
+}
+
+/// `text` as a URL to open when it already is one (`https://…`) or looks
+/// like a domain with an optional port and path (`docs.rs/gtk`).
+fn as_url(text: &str) -> Option<String> {
+    if text.is_empty() || text.contains(char::is_whitespace) {
+        return None;
+    }
+    // Checked for `://` first, as `localhost:8080` parses as scheme `localhost`
+    if let Some((_, rest)) = text.split_once("://") {
+        let known = glib::uri_parse_scheme(text)
+            .is_some_and(|scheme| ["http", "https", "ftp", "file"].contains(&scheme.to_lowercase().as_str()));
+        return (known && !rest.is_empty()).then(|| text.to_string());
+    }
+
+    let host_port = text.split(['/', '?', '#']).next()?;
+    let (host, port) = match host_port.rsplit_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (host_port, None),
+    };
+    if port.is_some_and(|port| port.is_empty() || !port.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let valid_labels = labels
+        .iter()
+        .all(|label| !label.is_empty() && !label.starts_with('-') && label.chars().all(|c| c.is_alphanumeric() || c == '-'));
+    let tld = labels.last()?;
+    let domain = labels.len() > 1 && tld.len() >= 2 && tld.chars().all(char::is_alphabetic);
+    if !valid_labels || !(domain || host == "localhost") {
+        return None;
+    }
+
+    let scheme = if host == "localhost" { "http" } else { "https" };
+    Some(format!("{}://{}", scheme, text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_with_a_scheme() {
+        assert_eq!(as_url("https://docs.rs/gtk"), Some(String::from("https://docs.rs/gtk")));
+        assert_eq!(as_url("FILE:///etc/hosts"), Some(String::from("FILE:///etc/hosts")));
+        assert_eq!(as_url("https://"), None);
+        assert_eq!(as_url("ssh://host"), None);
+    }
+
+    #[test]
+    fn domains_get_a_scheme() {
+        assert_eq!(as_url("docs.rs/gtk"), Some(String::from("https://docs.rs/gtk")));
+        assert_eq!(as_url("example.com:8080/?q=1"), Some(String::from("https://example.com:8080/?q=1")));
+        assert_eq!(as_url("localhost:3000"), Some(String::from("http://localhost:3000")));
+        assert_eq!(as_url("README.md"), Some(String::from("https://README.md")));
+    }
+
+    #[test]
+    fn not_urls() {
+        assert_eq!(as_url(""), None);
+        assert_eq!(as_url("rust docs.rs"), None);
+        assert_eq!(as_url("firefox"), None);
+        assert_eq!(as_url("v1.2"), None);
+        assert_eq!(as_url("a..b"), None);
+        assert_eq!(as_url("-x.com"), None);
+        assert_eq!(as_url("example.com:port"), None);
+        assert_eq!(as_url("example.com:"), None);
+    }
+
+    #[test]
+    fn file_names_rank_below_the_search() {
+        assert!(!clearly_url("next.js"));
+        assert!(!clearly_url("README.md"));
+        assert!(clearly_url("example.com"));
+        assert!(clearly_url("docs.rs/gtk"));
+        assert!(clearly_url("router.lan:8080"));
+        assert!(clearly_url("localhost"));
+        assert!(clearly_url("http://next.js"));
+    }
+
+    #[test]
+    fn bang_first_or_last() {
+        let web = WebConfig::default();
+        let (engine, rest) = split_bang(&web, "!gh gtk-rs  bindings").unwrap();
+        assert_eq!((engine.id.as_str(), rest.as_str()), ("github", "gtk-rs bindings"));
+        let (engine, rest) = split_bang(&web, "arch linux !w").unwrap();
+        assert_eq!((engine.id.as_str(), rest.as_str()), ("wikipedia", "arch linux"));
+        let (engine, rest) = split_bang(&web, "!g").unwrap();
+        assert_eq!((engine.id.as_str(), rest.as_str()), ("google", ""));
+    }
+
+    #[test]
+    fn unknown_or_inner_bangs_are_text() {
+        let web = WebConfig::default();
+        assert!(split_bang(&web, "hello !nope").is_none());
+        assert!(split_bang(&web, "a !g b").is_none());
+        assert!(split_bang(&web, "wow!").is_none());
+        assert!(split_bang(&web, "").is_none());
+        assert_eq!(bang_target(&web, "!aw pacman"), Some("ArchWiki"));
+    }
+}
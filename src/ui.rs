@@ -1,9 +1,15 @@
+use std::path::Path;
+use glib::translate::ToGlibPtr;
 use gtk::{
     ApplicationWindow, Box as GtkBox, Entry, Label, Orientation,
-    CssProvider, StyleContext,
-    gdk, prelude::*,
+    CssProvider, StyleContext, Image, Clipboard, Dialog, DialogFlags, ResponseType, TargetEntry, TargetFlags,
+    gdk, gdk_pixbuf::Pixbuf, prelude::*,
 };
 
+use crate::openers::get_openers;
+use crate::preview::PREVIEW_WIDTH;
+use crate::providers::{ResultIcon, SearchResult};
+
 
 pub fn apply_css() {
     let provider = CssProvider::new();
@@ -39,9 +45,33 @@ pub fn apply_css() {
                 background-color: rgba(255, 255, 255, 0.12); 
             }
 
+            box.result-row.selected label.result-label {
+                font-weight: bold;
+            }
+
             label.result-label {
                 font-size: 14px;
             }
+
+            label.result-hint {
+                color: #888;
+                font-size: 16px;
+            }
+
+            box.preview {
+                padding-left: 12px;
+                border-left: 1px solid rgba(255, 255, 255, 0.08);
+            }
+
+            label.preview-text {
+                font-family: monospace;
+                font-size: 11px;
+                color: #ccc;
+            }
+
+            label.preview-key {
+                color: #888;
+            }
         ").unwrap();
 
     StyleContext::add_provider_for_screen(
@@ -51,7 +81,9 @@ pub fn apply_css() {
     );
 }
 
-pub fn build_main_ui(app: &gtk::Application) -> (ApplicationWindow, Entry, Label, GtkBox) {
+/// Returns the window, the entry, the mode label, the results list and,
+/// unless `[config] preview = false`, the preview pane.
+pub fn build_main_ui(app: &gtk::Application) -> (ApplicationWindow, Entry, Label, GtkBox, Option<GtkBox>) {
     let window = ApplicationWindow::new(app);
 
     window.set_border_width(0);
@@ -99,13 +131,201 @@ pub fn build_main_ui(app: &gtk::Application) -> (ApplicationWindow, Entry, Label
     scroll.set_vexpand(true); 
     scroll.add(&result_box);
     
+    let content = GtkBox::new(Orientation::Horizontal, 12);
+    content.pack_start(&scroll, true, true, 0);
+
+    let preview = get_openers().preview.then(|| {
+        let preview = GtkBox::new(Orientation::Vertical, 8);
+        preview.style_context().add_class("preview");
+        preview.set_size_request(PREVIEW_WIDTH, -1);
+        // Shown once something is selected that can be previewed
+        preview.set_no_show_all(true);
+        content.pack_end(&preview, false, false, 0);
+        preview
+    });
+
     outer_vbox.pack_start(&titlebar, false, false, 0);
     outer_vbox.pack_start(&entry, false, false, 8);
-    outer_vbox.pack_end(&scroll, true, true, 0);
+    outer_vbox.pack_end(&content, true, true, 0);
 
 
     window.add(&outer_vbox);
 
-    (window, entry, mode_label, result_box)
+    (window, entry, mode_label, result_box, preview)
+}
+
+
+pub fn render_results(result_box: &GtkBox, results: &[SearchResult]) {
+    result_box.foreach(|child| result_box.remove(child));
+
+    for result in results.iter().take(50) {
+        let row = result_row(
+            &result.icon,
+            &highlight_matches(&result.title, &result.title_matches),
+            &highlight_matches(&result.subtitle, &result.subtitle_matches),
+        );
+        if !result.actions.is_empty() {
+            // Hint that Right opens more actions
+            let hint = Label::new(Some("›"));
+            hint.style_context().add_class("result-hint");
+            row.pack_end(&hint, false, false, 4);
+        }
+        result_box.pack_start(&row, false, false, 0);
+        row.show_all();
+    }
+}
+
+/// Lists the secondary actions of `result` in place of the results.
+pub fn render_actions(result_box: &GtkBox, result: &SearchResult) {
+    result_box.foreach(|child| result_box.remove(child));
+
+    for action in &result.actions {
+        let icon = action.icon.as_ref().unwrap_or(&result.icon);
+        let row = result_row(
+            icon,
+            &glib::markup_escape_text(&action.title),
+            &glib::markup_escape_text(&result.title),
+        );
+        result_box.pack_start(&row, false, false, 0);
+        row.show_all();
+    }
+}
+
+/// `title` and `subtitle` are Pango markup.
+fn result_row(icon: &ResultIcon, title: &str, subtitle: &str) -> GtkBox {
+    let row = GtkBox::new(Orientation::Horizontal, 6);
+    row.style_context().add_class("result-row");
+
+    row.pack_start(&icon_image(icon), false, false, 0);
+
+    let label = Label::new(None);
+    label.style_context().add_class("result-label");
+    label.set_use_markup(true);
+    label.set_xalign(0.0);
+    label.set_line_wrap(true);
+    label.set_max_width_chars(80);
+    label.set_ellipsize(pango::EllipsizeMode::End);
+
+    label.set_markup(&format!(
+        "<span foreground='#fff' weight='bold'>{}</span>\n<span size='small' foreground='#888'>{}</span>",
+        title,
+        subtitle
+    ));
+
+    row.pack_start(&label, true, true, 0);
+    row
+}
+
+/// Escapes `text` for markup, wrapping runs of matched chars in a
+/// highlight span.
+fn highlight_matches(text: &str, positions: &[usize]) -> String {
+    let mut markup = String::with_capacity(text.len());
+    let mut run = String::new();
+    let mut in_run = false;
+
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.contains(&i);
+        if matched != in_run {
+            flush_run(&mut markup, &mut run, in_run);
+            in_run = matched;
+        }
+        run.push(c);
+    }
+    flush_run(&mut markup, &mut run, in_run);
+
+    markup
+}
+
+fn flush_run(markup: &mut String, run: &mut String, matched: bool) {
+    if run.is_empty() {
+        return;
+    }
+    let escaped = glib::markup_escape_text(run);
+    if matched {
+        markup.push_str(&format!("<span foreground='#8ab4f8' underline='single'>{}</span>", escaped));
+    } else {
+        markup.push_str(&escaped);
+    }
+    run.clear();
+}
+
+fn icon_image(icon: &ResultIcon) -> Image {
+    match icon {
+        ResultIcon::Name(name) => Image::from_icon_name(Some(name.as_str()), gtk::IconSize::SmallToolbar),
+        ResultIcon::Path(path) => match Pixbuf::from_file_at_size(path, 24, 24) {
+            Ok(pixbuf) => Image::from_pixbuf(Some(&pixbuf)),
+            Err(_) => Image::from_icon_name(Some("application-x-executable"), gtk::IconSize::SmallToolbar),
+        },
+    }
+}
+
+/// Puts `text` on the clipboard and hands it to the clipboard manager, so
+/// it is still there after the launcher closes.
+pub fn copy_text(text: &str) {
+    let clipboard = Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+    clipboard.set_text(text);
+    clipboard.store();
 }
 
+/// Puts `path` on the clipboard the way file managers do, so it pastes
+/// as a file there and as its path in text fields.
+pub fn copy_file(path: &Path) {
+    let uri = gio::File::for_path(path).uri().to_string();
+    let text = path.to_string_lossy().to_string();
+
+    let targets = [
+        TargetEntry::new("x-special/gnome-copied-files", TargetFlags::empty(), 0),
+        TargetEntry::new("text/uri-list", TargetFlags::empty(), 1),
+        TargetEntry::new("UTF8_STRING", TargetFlags::empty(), 2),
+    ];
+
+    let clipboard = Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+    clipboard.set_with_data(&targets, move |_, selection, info| match info {
+        0 => selection.set(&selection.target(), 8, format!("copy\n{}", uri).as_bytes()),
+        1 => {
+            selection.set_uris(&[&uri]);
+        }
+        _ => {
+            selection.set_text(&text);
+        }
+    });
+
+    // gtk-rs has no binding for this; without it the clipboard manager
+    // only keeps text
+    unsafe {
+        gtk::ffi::gtk_clipboard_set_can_store(clipboard.to_glib_none().0, std::ptr::null(), 0);
+    }
+    clipboard.store();
+}
+
+/// Asks for a new name in a small dialog, with `name` filled in and
+/// selected up to its extension, over `parent`. `None` when cancelled or
+/// left unchanged.
+pub fn ask_new_name(parent: &gtk::Window, name: &str) -> Option<String> {
+    let dialog = Dialog::with_buttons(
+        Some("Rename"),
+        Some(parent),
+        DialogFlags::MODAL,
+        &[("Cancel", ResponseType::Cancel), ("Rename", ResponseType::Accept)],
+    );
+    dialog.set_default_response(ResponseType::Accept);
+
+    let entry = Entry::new();
+    entry.set_text(name);
+    entry.set_activates_default(true);
+    entry.set_margin(12);
+    dialog.content_area().add(&entry);
+    dialog.show_all();
+
+    let stem_len = match name.rfind('.') {
+        Some(dot) if dot > 0 => name[..dot].chars().count(),
+        _ => name.chars().count(),
+    };
+    entry.select_region(0, stem_len as i32);
+
+    let response = dialog.run();
+    let new_name = entry.text().trim().to_string();
+    dialog.close();
+
+    (response == ResponseType::Accept && !new_name.is_empty() && new_name != name).then_some(new_name)
+}
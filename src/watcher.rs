@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use notify::event::ModifyKind;
+use notify::{ErrorKind, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::index::{FileIndex, IndexChanges};
+use crate::openers::FilesConfig;
+
+/// Events are gathered for this long before the index is touched, so a
+/// burst (an extracted archive, a build) costs one update.
+const SETTLE: Duration = Duration::from_millis(500);
+/// The cache is written at most this often while events keep coming.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+pub type SharedIndex = Arc<RwLock<Option<FileIndex>>>;
+
+/// Keeps `index` in sync with the file system, never returning: one
+/// inotify watch per indexed directory, so creations, deletions and
+/// renames show up as they happen. When the watch limit
+/// (`fs.inotify.max_user_watches`) runs out, or watching isn't possible at
+/// all, it falls back to rescanning every `config.rescan_interval`.
+pub fn run(index: &SharedIndex, config: &FilesConfig) {
+    if config.watch {
+        watch(index, config);
+    }
+    poll(index, config);
+}
+
+/// Runs until watching stops being possible, then returns so the caller
+/// can fall back to polling.
+fn watch(index: &SharedIndex, config: &FilesConfig) {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = match RecommendedWatcher::new(tx, notify::Config::default()) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("File watching unavailable, rescanning periodically instead: {}", e);
+            return;
+        }
+    };
+
+    let dirs: Vec<String> = match index.read() {
+        Ok(index) => index.iter().flat_map(|i| i.dirs()).map(String::from).collect(),
+        Err(_) => return,
+    };
+    if !add_watches(&mut watcher, &dirs) {
+        return;
+    }
+
+    let mut last_save = Instant::now();
+
+    loop {
+        let mut dirty = HashSet::new();
+        let mut rescan = false;
+
+        // Block for the first event, then gather until things settle
+        let first = match rx.recv() {
+            Ok(event) => event,
+            Err(_) => return,
+        };
+        let mut pending = Some(first);
+        while let Some(event) = pending.take() {
+            match event {
+                Ok(event) => {
+                    rescan |= event.need_rescan();
+                    if matches!(event.kind, EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))) {
+                        for path in &event.paths {
+                            if let Some(parent) = path.parent().and_then(Path::to_str) {
+                                dirty.insert(parent.to_string());
+                            }
+                        }
+                    }
+                }
+                Err(e) if matches!(e.kind, ErrorKind::MaxFilesWatch) => {
+                    eprintln!("Ran out of inotify watches, rescanning periodically instead");
+                    return;
+                }
+                Err(_) => {}
+            }
+
+            pending = match rx.recv_timeout(SETTLE) {
+                Ok(event) => Some(event),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+        }
+
+        let changes = match index.read() {
+            Ok(guard) => match guard.as_ref() {
+                // The kernel queue overflowed, so events were lost
+                Some(index) if rescan => index.changes(config),
+                Some(index) => index.changes_in(&dirty, config),
+                None => continue,
+            },
+            Err(_) => return,
+        };
+        let Some(added) = apply(index, changes) else {
+            return;
+        };
+
+        if !add_watches(&mut watcher, &added) {
+            return;
+        }
+
+        if last_save.elapsed() >= SAVE_INTERVAL {
+            save(index);
+            last_save = Instant::now();
+        }
+    }
+}
+
+/// Returns false once the watch limit is hit.
+fn add_watches(watcher: &mut RecommendedWatcher, dirs: &[String]) -> bool {
+    for dir in dirs {
+        if let Err(e) = watcher.watch(Path::new(dir), RecursiveMode::NonRecursive) {
+            if matches!(e.kind, ErrorKind::MaxFilesWatch) {
+                eprintln!("Ran out of inotify watches, rescanning periodically instead");
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn poll(index: &SharedIndex, config: &FilesConfig) {
+    loop {
+        thread::sleep(Duration::from_secs(config.rescan_interval));
+
+        let changes = match index.read() {
+            Ok(guard) => match guard.as_ref() {
+                Some(index) => index.changes(config),
+                None => continue,
+            },
+            Err(_) => return,
+        };
+        if changes.is_empty() {
+            continue;
+        }
+        if apply(index, changes).is_none() {
+            return;
+        }
+        save(index);
+    }
+}
+
+/// Applies changes found under the read lock. Only this thread writes to
+/// the index once it is loaded, so it can't have moved on meanwhile and
+/// the write lock is held just for the update, not the disk scan.
+/// Returns the added directories, `None` if the lock is poisoned.
+fn apply(index: &SharedIndex, changes: IndexChanges) -> Option<Vec<String>> {
+    if changes.is_empty() {
+        return Some(Vec::new());
+    }
+    let mut guard = index.write().ok()?;
+    Some(guard.as_mut().map(|index| index.apply(changes)).unwrap_or_default())
+}
+
+fn save(index: &SharedIndex) {
+    if let Ok(guard) = index.read() {
+        if let Some(index) = guard.as_ref() {
+            index.save();
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::providers::{CancelToken, ProviderRegistry, SearchResult};
+
+/// How long typing has to pause before a search starts.
+const DEBOUNCE: Duration = Duration::from_millis(80);
+
+pub struct SearchResponse {
+    /// Entry text the search was made for.
+    pub text: String,
+    pub provider: &'static str,
+    pub query: String,
+    pub results: Vec<SearchResult>,
+}
+
+/// Runs searches on a background thread so the GTK main loop never waits
+/// on a provider. Requests are debounced, a newer request cancels the one
+/// in flight, and only the results of the latest request are delivered.
+pub struct SearchWorker {
+    requests: mpsc::Sender<(u64, String)>,
+    latest: Arc<AtomicU64>,
+}
+
+impl SearchWorker {
+    /// Starts the worker thread. `on_results` runs on the main loop.
+    pub fn spawn(registry: Arc<ProviderRegistry>, on_results: impl Fn(SearchResponse) + 'static) -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(u64, String)>();
+        let (response_tx, response_rx) = async_channel::unbounded::<(u64, SearchResponse)>();
+        let latest = Arc::new(AtomicU64::new(0));
+
+        let worker_latest = latest.clone();
+        thread::spawn(move || {
+            while let Ok(mut request) = request_rx.recv() {
+                // Keep taking newer requests until typing pauses
+                while let Ok(newer) = request_rx.recv_timeout(DEBOUNCE) {
+                    request = newer;
+                }
+
+                let (generation, text) = request;
+                let cancel = CancelToken::new(worker_latest.clone(), generation);
+                if cancel.is_cancelled() {
+                    continue;
+                }
+
+                let (provider, query, results) = registry.search(&text, &cancel);
+                if cancel.is_cancelled() {
+                    continue;
+                }
+
+                let response = SearchResponse {
+                    provider,
+                    query: query.to_string(),
+                    text,
+                    results,
+                };
+                if response_tx.send_blocking((generation, response)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let main_latest = latest.clone();
+        glib::spawn_future_local(async move {
+            while let Ok((generation, response)) = response_rx.recv().await {
+                // A newer request may have been made while this one was queued
+                if generation == main_latest.load(Ordering::SeqCst) {
+                    on_results(response);
+                }
+            }
+        });
+
+        Self { requests: request_tx, latest }
+    }
+
+    /// Queues a search for `text`, cancelling any search still running.
+    pub fn request(&self, text: &str) {
+        let generation = self.latest.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.requests.send((generation, text.to_string()));
+    }
+}